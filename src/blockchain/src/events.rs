@@ -0,0 +1,166 @@
+// Typed events of the chain. Every event gets an increasing id and the latest
+// ones of each game are kept in a bounded journal, so a client that connects
+// late, reconnects or falls behind the broadcast channel still gets what it
+// missed. A busy game only evicts its own backlog, never the one of a quiet game.
+//
+// Ids come from the ledger, not from the order of emission: the events of the
+// command recorded in block h are numbered (h << 20) + 0, 1, ... and the
+// rejections that come after block h get (h << 20) + 2^19 + 0, 1, ... Replaying
+// the log after a restart gives every event of an accepted command the id it had
+// before, so Last-Event-ID keeps pointing to the same place. Rejections are not
+// in the log and are not replayed, but the ids after them do not shift.
+
+use futures::stream::{self, Stream};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use fleetcore::{ChainEvent, EventKind};
+
+// Events of each game kept for late and lagging subscribers
+const JOURNAL_CAPACITY: usize = 200;
+// Events buffered per subscriber before it lags
+const CHANNEL_CAPACITY: usize = 100;
+// Bits of an id left for the events of one block
+const BLOCK_SHIFT: u32 = 20;
+// First index of the rejections that follow a block
+const REJECTED_BASE: u64 = 1 << 19;
+
+pub struct EventLog {
+    journals: BTreeMap<Option<String>, VecDeque<ChainEvent>>, // by game, None for the chain
+    block: u64,    // height of the block that records the command being applied
+    index: u64,    // events emitted for that command
+    rejected: u64, // rejections since the last block
+    tx: broadcast::Sender<ChainEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        let (tx, _rx) = broadcast::channel::<ChainEvent>(CHANNEL_CAPACITY);
+        EventLog {
+            journals: BTreeMap::new(),
+            block: 1,
+            index: 0,
+            rejected: 0,
+            tx,
+        }
+    }
+
+    // A command is about to be applied, to be recorded in the block at height
+    pub fn begin_block(&mut self, height: u64) {
+        if height != self.block {
+            self.rejected = 0;
+        }
+        self.block = height;
+        self.index = 0;
+    }
+
+    // Event of the command being applied
+    pub fn emit(&mut self, gameid: Option<&str>, kind: EventKind) {
+        let id = (self.block << BLOCK_SHIFT) + self.index;
+        self.index += 1;
+        self.push(id, gameid, kind);
+    }
+
+    // Rejection of the command being applied: it comes after the last block
+    pub fn emit_rejected(&mut self, gameid: Option<&str>, kind: EventKind) {
+        let id = ((self.block - 1) << BLOCK_SHIFT) + REJECTED_BASE + self.rejected;
+        self.rejected += 1;
+        self.push(id, gameid, kind);
+    }
+
+    fn push(&mut self, id: u64, gameid: Option<&str>, kind: EventKind) {
+        let event = ChainEvent {
+            id,
+            gameid: gameid.map(str::to_string),
+            kind,
+        };
+        let journal = self.journals.entry(event.gameid.clone()).or_default();
+        if journal.len() == JOURNAL_CAPACITY {
+            journal.pop_front();
+        }
+        journal.push_back(event.clone());
+        // Sem subscritores o envio falha, o evento fica na mesma no journal
+        let _ = self.tx.send(event);
+    }
+
+    // Events of every journal after the given id, in id order. Older ones may
+    // already be gone.
+    fn since(&self, after: u64) -> VecDeque<ChainEvent> {
+        let mut events: Vec<ChainEvent> = self
+            .journals
+            .values()
+            .flat_map(|journal| journal.iter().filter(|e| e.id > after).cloned())
+            .collect();
+        events.sort_by_key(|e| e.id);
+        events.into()
+    }
+}
+
+struct Subscription {
+    log: Arc<Mutex<EventLog>>,
+    rx: broadcast::Receiver<ChainEvent>,
+    pending: VecDeque<ChainEvent>,
+    last: u64, // id of the last event handed out
+}
+
+// Every event after the given id (the whole journal for a new client) followed
+// by the live ones. A subscriber that lags behind the channel picks the events it
+// skipped from the journal instead of losing the connection.
+pub fn subscribe(log: Arc<Mutex<EventLog>>, after: Option<u64>) -> impl Stream<Item = ChainEvent> {
+    // Backlog e receiver sob o mesmo lock: nenhum evento se perde entre os dois
+    let (pending, rx) = {
+        let events = log.lock().unwrap();
+        (events.since(after.unwrap_or(0)), events.tx.subscribe())
+    };
+    let subscription = Subscription {
+        log,
+        rx,
+        pending,
+        last: after.unwrap_or(0),
+    };
+
+    stream::unfold(subscription, |mut sub| async move {
+        loop {
+            if let Some(event) = sub.pending.pop_front() {
+                sub.last = event.id;
+                return Some((event, sub));
+            }
+            match sub.rx.recv().await {
+                Ok(event) if event.id > sub.last => sub.pending.push_back(event),
+                // Já entregue a partir do journal
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => sub.pending = sub.log.lock().unwrap().since(sub.last),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busy_game_does_not_evict_quiet_game() {
+        let mut log = EventLog::new();
+        log.begin_block(1);
+        log.emit(Some("quiet"), EventKind::GameCancelled);
+        for height in 2..2 + JOURNAL_CAPACITY as u64 * 2 {
+            log.begin_block(height);
+            log.emit(Some("busy"), EventKind::GameCancelled);
+        }
+
+        let events = log.since(0);
+        assert_eq!(events.len(), JOURNAL_CAPACITY + 1);
+        assert_eq!(events[0].gameid.as_deref(), Some("quiet"));
+        // O jogo ativo só perde os seus eventos mais antigos, e a ordem é a dos ids
+        assert!(events
+            .iter()
+            .zip(events.iter().skip(1))
+            .all(|(a, b)| a.id < b.id));
+        assert_eq!(log.since(events[1].id).len(), JOURNAL_CAPACITY - 1);
+    }
+}
@@ -0,0 +1,1049 @@
+// Game state kept by the chain, as an explicit state machine:
+//
+//   Lobby -> InProgress(AwaitingFire <-> AwaitingReport -> AwaitingWin) -> Finished
+//     \-> Cancelled
+//
+// Once a single fleet is left in play the game waits for that fleet to prove
+// its win, which is what finishes it.
+//
+// A game created with a TurnTimeout also has a deadline for the player it waits
+// for; the chain settles an expired turn through time_out or claim_timeout.
+//
+// Every command goes through one of the transitions below. A transition checks
+// everything first and only then mutates the game, so a rejected command never
+// leaves a game half updated.
+
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use risc0_zkvm::Digest;
+use std::{collections::BTreeMap, fmt};
+
+use fleetcore::{
+    Coord, ErrorCode, GameRules, GameState, HistoryEntry, Phase, PlayerState, TimeoutPolicy, Turn,
+    TurnPolicy, TurnTimeout,
+};
+
+pub struct Player {
+    pub current_state: Digest,
+    // criar tabela para cada jogador para ter shots
+    pub shots: Vec<u8>, // 0 = sem tiro, 1 = falha, 2 = acerto (width * height posições)
+    pub hit_count: u32,
+    pub seq: u32,         // sequence number of the player's last accepted command
+    pub pubkey: [u8; 32], // Ed25519 key registered by join, signs every command
+    pub forfeited: bool,  // deixou esgotar o prazo de uma jogada, está fora do jogo
+    pub ship_cells: u32,  // células de barco da frota, segundo as regras do jornal do join
+}
+
+impl Player {
+    // Todos os barcos afundados segundo os reports aceites
+    pub fn eliminated(&self) -> bool {
+        self.hit_count >= self.ship_cells
+    }
+
+    // Fora do jogo: não joga nem pode ser alvo
+    pub fn out(&self) -> bool {
+        self.forfeited || self.eliminated()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GameError {
+    GameStarted,
+    GameFinished,
+    GameNotStarted,
+    GameCancelled,
+    GameFull(u32),
+    DifferentRules,
+    AlreadyJoined,
+    NotEnoughPlayers(u32),
+    UnknownPlayer(String),
+    UnknownTarget(String),
+    NotYourTurn,
+    SelfTarget,
+    AlreadyFired(Coord),
+    FleetOut(String),
+    NotTimedOut,
+    ClaimNotAllowed,
+    ReportPending,
+    WinPending,
+    NoShotPending,
+    NotTarget,
+    ReportMismatch { expected: Coord, got: Coord },
+    OutsideBoard(Coord),
+    BoardMismatch,
+    FleetsAfloat(Vec<String>),
+    OutOfSequence { expected: u32, got: u32 },
+}
+
+impl GameError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            GameError::GameStarted => ErrorCode::GameStarted,
+            GameError::GameFinished => ErrorCode::GameFinished,
+            GameError::GameNotStarted => ErrorCode::GameNotStarted,
+            GameError::GameCancelled => ErrorCode::GameCancelled,
+            GameError::GameFull(_) => ErrorCode::GameFull,
+            GameError::DifferentRules => ErrorCode::DifferentRules,
+            GameError::AlreadyJoined => ErrorCode::AlreadyJoined,
+            GameError::NotEnoughPlayers(_) => ErrorCode::NotEnoughPlayers,
+            GameError::UnknownPlayer(_) => ErrorCode::UnknownPlayer,
+            GameError::UnknownTarget(_) => ErrorCode::UnknownTarget,
+            GameError::NotYourTurn => ErrorCode::NotYourTurn,
+            GameError::SelfTarget => ErrorCode::SelfTarget,
+            GameError::AlreadyFired(_) => ErrorCode::AlreadyFired,
+            GameError::FleetOut(_) => ErrorCode::FleetOut,
+            GameError::NotTimedOut => ErrorCode::NotTimedOut,
+            GameError::ClaimNotAllowed => ErrorCode::ClaimNotAllowed,
+            GameError::ReportPending => ErrorCode::ReportPending,
+            GameError::WinPending => ErrorCode::WinPending,
+            GameError::NoShotPending => ErrorCode::NoShotPending,
+            GameError::NotTarget => ErrorCode::NotTarget,
+            GameError::ReportMismatch { .. } => ErrorCode::ReportMismatch,
+            GameError::OutsideBoard(_) => ErrorCode::OutsideBoard,
+            GameError::BoardMismatch => ErrorCode::BoardMismatch,
+            GameError::FleetsAfloat(_) => ErrorCode::FleetsAfloat,
+            GameError::OutOfSequence { .. } => ErrorCode::OutOfSequence,
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::GameStarted => write!(f, "Game already started"),
+            GameError::GameFinished => write!(f, "Game already finished"),
+            GameError::GameNotStarted => write!(f, "Game has not started"),
+            GameError::GameCancelled => write!(f, "Game was cancelled"),
+            GameError::GameFull(max) => write!(f, "Game is full ({} players)", max),
+            GameError::DifferentRules => write!(f, "Game uses different rules"),
+            GameError::AlreadyJoined => write!(f, "Player already in game"),
+            GameError::NotEnoughPlayers(min) => {
+                write!(f, "Game needs at least {} players", min)
+            }
+            GameError::UnknownPlayer(p) => write!(f, "Player {} is not in this game", p),
+            GameError::UnknownTarget(p) => write!(f, "Target player {} is not in this game", p),
+            GameError::NotYourTurn => write!(f, "Not your turn"),
+            GameError::SelfTarget => write!(f, "You cannot fire at your own fleet"),
+            GameError::AlreadyFired(pos) => write!(f, "Position {} was already fired at", pos),
+            GameError::FleetOut(p) => write!(f, "Fleet {} is out of the game", p),
+            GameError::NotTimedOut => write!(f, "The turn has not timed out"),
+            GameError::ClaimNotAllowed => write!(f, "This game does not allow timeout claims"),
+            GameError::ReportPending => write!(f, "A shot is waiting to be reported"),
+            GameError::WinPending => {
+                write!(f, "Only the last fleet afloat is left to claim the win")
+            }
+            GameError::NoShotPending => write!(f, "There is no shot to report"),
+            GameError::NotTarget => write!(f, "You are not the target of the shot."),
+            GameError::ReportMismatch { expected, got } => write!(
+                f,
+                "Report mismatch: expected position {}, got {}",
+                expected, got
+            ),
+            GameError::OutsideBoard(pos) => write!(f, "Position {} is outside the board", pos),
+            GameError::BoardMismatch => write!(f, "Board does not match commitment"),
+            GameError::FleetsAfloat(fleets) => {
+                write!(f, "Other fleets are still afloat: {}", fleets.join(", "))
+            }
+            GameError::OutOfSequence { expected, got } => write!(
+                f,
+                "Out-of-sequence command: expected sequence number {}, got {}",
+                expected, got
+            ),
+        }
+    }
+}
+
+pub struct Game {
+    pub pmap: BTreeMap<String, Player>, // ordenado para que o replay escolha os mesmos jogadores
+    pub order: Vec<String>,             // fleet ids by join order
+    pub rules: GameRules,               // escolhidas por quem criou o jogo
+    pub min_players: u32,
+    pub max_players: u32,
+    pub owner: [u8; 32], // chave de quem criou o jogo, assina o start e o cancel
+    pub turns: TurnPolicy, // escolhida por quem criou o jogo, fora das regras do jornal
+    pub timeout: Option<TurnTimeout>,
+    pub turn_since: u64, // timestamp do bloco que deu a vez ao jogador de quem se espera
+    pub phase: Phase,
+    pub history: Vec<HistoryEntry>, // accepted commands, in order
+    pub seed: u64,                  // publicado no GameState
+    rng: ChaCha8Rng,                // usado pela política SeededRandom
+}
+
+impl Game {
+    pub fn new(
+        rules: GameRules,
+        seed: u64,
+        min_players: u32,
+        max_players: u32,
+        owner: [u8; 32],
+        turns: TurnPolicy,
+        timeout: Option<TurnTimeout>,
+    ) -> Self {
+        Game {
+            pmap: BTreeMap::new(),
+            order: Vec::new(),
+            rules,
+            min_players,
+            max_players,
+            owner,
+            turns,
+            timeout,
+            turn_since: 0,
+            phase: Phase::Lobby,
+            history: Vec::new(),
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    // Public view of the game, sent back to the clients
+    pub fn state(&self, gameid: &str) -> GameState {
+        GameState {
+            gameid: gameid.to_string(),
+            rules: self.rules.clone(),
+            seed: self.seed,
+            min_players: self.min_players,
+            max_players: self.max_players,
+            turns: self.turns,
+            timeout: self.timeout,
+            deadline: self.deadline(),
+            phase: self.phase.clone(),
+            players: self
+                .order
+                .iter()
+                .map(|fleetid| PlayerState {
+                    fleetid: fleetid.clone(),
+                    forfeited: self.pmap[fleetid].forfeited,
+                    eliminated: self.pmap[fleetid].eliminated(),
+                    hits: self.pmap[fleetid].hit_count,
+                    seq: self.pmap[fleetid].seq,
+                    shots: self.pmap[fleetid].shots.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    // Player whose turn it is to fire, if any
+    pub fn next_player(&self) -> Option<&String> {
+        match &self.phase {
+            Phase::InProgress(Turn::AwaitingFire { player }) => Some(player),
+            _ => None,
+        }
+    }
+
+    // Key that must have signed the commands of a player
+    pub fn pubkey(&self, fleetid: &str) -> Option<&[u8; 32]> {
+        self.pmap.get(fleetid).map(|p| &p.pubkey)
+    }
+
+    // Shot waiting for its report, as (position, target)
+    pub fn pending_shot(&self) -> Option<(Coord, &String)> {
+        match &self.phase {
+            Phase::InProgress(Turn::AwaitingReport { pos, target, .. }) => Some((*pos, target)),
+            _ => None,
+        }
+    }
+
+    // Player the game is waiting for: the one to fire, or the target of the
+    // pending shot
+    pub fn stalled_player(&self) -> Option<&String> {
+        match &self.phase {
+            Phase::InProgress(Turn::AwaitingFire { player }) => Some(player),
+            Phase::InProgress(Turn::AwaitingReport { target, .. }) => Some(target),
+            _ => None,
+        }
+    }
+
+    // When the current turn expires, for games with a turn timeout
+    pub fn deadline(&self) -> Option<u64> {
+        match (&self.timeout, &self.phase) {
+            // A última frota reclama a vitória quando quiser
+            (_, Phase::InProgress(Turn::AwaitingWin { .. })) => None,
+            // Saturada: um prazo para lá de u64 nunca chega
+            (Some(timeout), Phase::InProgress(_)) => {
+                Some(self.turn_since.saturating_add(timeout.seconds))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn expired(&self, now: u64) -> bool {
+        self.deadline().is_some_and(|deadline| now >= deadline)
+    }
+
+    // A command accepted at the given time gave the turn to someone: their
+    // deadline counts from now
+    pub fn restart_clock(&mut self, now: u64) {
+        self.turn_since = now;
+    }
+
+    pub fn winner(&self) -> Option<&String> {
+        match &self.phase {
+            Phase::Finished { winner } => Some(winner),
+            _ => None,
+        }
+    }
+
+    // Lobby -> InProgress, locking the roster. The turn order is the join order
+    pub fn start(&mut self) -> Result<(), GameError> {
+        self.check_lobby()?;
+        if (self.order.len() as u32) < self.min_players {
+            return Err(GameError::NotEnoughPlayers(self.min_players));
+        }
+
+        self.phase = Phase::InProgress(Turn::AwaitingFire {
+            player: self.order[0].clone(),
+        });
+        Ok(())
+    }
+
+    // Lobby -> Cancelled
+    pub fn cancel(&mut self) -> Result<(), GameError> {
+        self.check_lobby()?;
+        self.phase = Phase::Cancelled;
+        Ok(())
+    }
+
+    // Lobby -> Lobby
+    pub fn join(
+        &mut self,
+        fleetid: &str,
+        seq: u32,
+        board: Digest,
+        rules: &GameRules,
+        pubkey: [u8; 32],
+    ) -> Result<(), GameError> {
+        self.check_lobby()?;
+        if self.order.len() as u32 >= self.max_players {
+            return Err(GameError::GameFull(self.max_players));
+        }
+        // Todos os jogadores jogam com as regras escolhidas na criação do jogo
+        if &self.rules != rules {
+            return Err(GameError::DifferentRules);
+        }
+        if self.pmap.contains_key(fleetid) {
+            return Err(GameError::AlreadyJoined);
+        }
+        if seq != 0 {
+            return Err(GameError::OutOfSequence {
+                expected: 0,
+                got: seq,
+            });
+        }
+
+        self.pmap.insert(
+            fleetid.to_string(),
+            Player {
+                current_state: board,
+                shots: vec![0; self.rules.cells()],
+                hit_count: 0,
+                seq,
+                pubkey,
+                forfeited: false,
+                ship_cells: rules.fleet_cells() as u32,
+            },
+        );
+        self.order.push(fleetid.to_string());
+        Ok(())
+    }
+
+    // AwaitingFire -> AwaitingReport
+    pub fn fire(
+        &mut self,
+        shooter: &str,
+        seq: u32,
+        board: Digest,
+        target: &str,
+        pos: Coord,
+    ) -> Result<(), GameError> {
+        self.check_seq(shooter, seq)?;
+        self.check_turn(shooter)?;
+        if !self.rules.contains(&pos) {
+            return Err(GameError::OutsideBoard(pos));
+        }
+        // O board do atirador tem de abrir o compromisso registado no join
+        self.check_board(shooter, board)?;
+        // O alvo é identificado pelo fleet id, a mesma chave do pmap
+        if target == shooter {
+            return Err(GameError::SelfTarget);
+        }
+        let index = pos.index(self.rules.width);
+        match self.pmap.get(target) {
+            None => return Err(GameError::UnknownTarget(target.to_string())),
+            Some(player) if player.out() => return Err(GameError::FleetOut(target.to_string())),
+            Some(player) if player.shots[index] != 0 => return Err(GameError::AlreadyFired(pos)),
+            Some(_) => {}
+        }
+
+        self.pmap.get_mut(shooter).unwrap().seq = seq;
+        // Passa a 2 se o report confirmar o acerto
+        self.pmap.get_mut(target).unwrap().shots[index] = 1;
+        self.phase = Phase::InProgress(Turn::AwaitingReport {
+            shooter: shooter.to_string(),
+            target: target.to_string(),
+            pos,
+        });
+        Ok(())
+    }
+
+    // AwaitingReport -> AwaitingFire, clearing the shot and passing the turn, or
+    // AwaitingReport -> AwaitingWin when the hit sinks the last fleet but one
+    pub fn report(
+        &mut self,
+        fleetid: &str,
+        seq: u32,
+        board: Digest,
+        next_board: Digest,
+        pos: Coord,
+        hit: bool,
+    ) -> Result<(), GameError> {
+        self.check_seq(fleetid, seq)?;
+        let shooter = match &self.phase {
+            Phase::Lobby => return Err(GameError::GameNotStarted),
+            Phase::Finished { .. } => return Err(GameError::GameFinished),
+            Phase::Cancelled => return Err(GameError::GameCancelled),
+            Phase::InProgress(Turn::AwaitingFire { .. } | Turn::AwaitingWin { .. }) => {
+                return Err(GameError::NoShotPending)
+            }
+            Phase::InProgress(Turn::AwaitingReport {
+                target,
+                pos: expected,
+                shooter,
+            }) => {
+                // O report deve ser feito pelo jogador que foi atingido
+                if target != fleetid {
+                    return Err(GameError::NotTarget);
+                }
+                if *expected != pos {
+                    return Err(GameError::ReportMismatch {
+                        expected: *expected,
+                        got: pos,
+                    });
+                }
+                shooter.clone()
+            }
+        };
+        // O report tem de partir do board comprometido pelo jogador
+        self.check_board(fleetid, board)?;
+
+        let index = pos.index(self.rules.width);
+        let player = self.pmap.get_mut(fleetid).unwrap();
+        player.seq = seq;
+        if hit {
+            player.shots[index] = 2;
+            player.hit_count += 1;
+        }
+        // O board do jogador passa a ser o que resulta do tiro
+        player.current_state = next_board;
+        // Com uma só frota em jogo já não há tiros: resta-lhe provar o win
+        let in_play = self.in_play();
+        if in_play.len() == 1 {
+            self.phase = Phase::InProgress(Turn::AwaitingWin {
+                player: in_play[0].clone(),
+            });
+            return Ok(());
+        }
+        // Só agora, com o tiro resolvido, a vez passa segundo a política do jogo
+        let next = self.pick_next(&shooter, hit);
+        self.phase = Phase::InProgress(Turn::AwaitingFire { player: next });
+        Ok(())
+    }
+
+    // AwaitingFire -> AwaitingFire, the player passing the turn without firing
+    pub fn wave(&mut self, fleetid: &str, seq: u32, board: Digest) -> Result<(), GameError> {
+        self.check_seq(fleetid, seq)?;
+        self.check_turn(fleetid)?;
+        // O wave tem de ser provado sobre o board comprometido pelo jogador
+        self.check_board(fleetid, board)?;
+
+        self.pmap.get_mut(fleetid).unwrap().seq = seq;
+        let next = self.pick_next(fleetid, false);
+        self.phase = Phase::InProgress(Turn::AwaitingFire { player: next });
+        Ok(())
+    }
+
+    // AwaitingWin -> Finished, claimed by the last fleet afloat
+    pub fn win(&mut self, fleetid: &str, seq: u32, board: Digest) -> Result<(), GameError> {
+        self.check_seq(fleetid, seq)?;
+        match self.phase {
+            Phase::Lobby => return Err(GameError::GameNotStarted),
+            Phase::Finished { .. } => return Err(GameError::GameFinished),
+            Phase::Cancelled => return Err(GameError::GameCancelled),
+            Phase::InProgress(_) => {}
+        }
+        // A prova diz que o board comprometido ainda tem barcos à tona
+        self.check_board(fleetid, board)?;
+
+        // Todas as outras frotas têm de estar fora do jogo segundo os reports aceites
+        let afloat: Vec<String> = self
+            .pmap
+            .iter()
+            .filter(|(k, p)| *k != fleetid && !p.out())
+            .map(|(k, _)| k.clone())
+            .collect();
+        if !afloat.is_empty() {
+            return Err(GameError::FleetsAfloat(afloat));
+        }
+
+        self.pmap.get_mut(fleetid).unwrap().seq = seq;
+        self.phase = Phase::Finished {
+            winner: fleetid.to_string(),
+        };
+        Ok(())
+    }
+
+    // InProgress -> InProgress, applying the game's timeout policy to
+    // the player who let the deadline expire. Returns that player
+    pub fn time_out(&mut self, now: u64) -> Result<String, GameError> {
+        // Num jogo com Claim a chain não faz nada, espera que alguém reclame
+        let policy = match self.timeout {
+            Some(timeout) if timeout.policy != TimeoutPolicy::Claim => timeout.policy,
+            _ => return Err(GameError::NotTimedOut),
+        };
+        let stalled = self.stalled_turn()?;
+        if !self.expired(now) {
+            return Err(GameError::NotTimedOut);
+        }
+
+        self.settle_timeout(&stalled, policy == TimeoutPolicy::Forfeit);
+        Ok(stalled)
+    }
+
+    // InProgress -> InProgress, claimed by a player over another who let the
+    // deadline expire in a game whose policy is Claim. The stalled player is
+    // taken out as with Forfeit; with one fleet left it awaits that fleet's win.
+    // Returns the stalled player
+    pub fn claim_timeout(&mut self, fleetid: &str, now: u64) -> Result<String, GameError> {
+        match self.timeout {
+            Some(timeout) if timeout.policy == TimeoutPolicy::Claim => {}
+            _ => return Err(GameError::ClaimNotAllowed),
+        }
+        let stalled = self.stalled_turn()?;
+        match self.pmap.get(fleetid) {
+            None => return Err(GameError::UnknownPlayer(fleetid.to_string())),
+            Some(player) if player.out() => return Err(GameError::FleetOut(fleetid.to_string())),
+            Some(_) => {}
+        }
+        // Quem deixou o prazo esgotar não pode reclamar
+        if stalled == fleetid || !self.expired(now) {
+            return Err(GameError::NotTimedOut);
+        }
+
+        self.settle_timeout(&stalled, true);
+        Ok(stalled)
+    }
+
+    // Resolve a jogada de quem deixou esgotar o prazo, tirando-o do jogo se
+    // forfeit; com uma só frota em jogo fica à espera do win dela
+    fn settle_timeout(&mut self, stalled: &str, forfeit: bool) {
+        // Um tiro por reportar não pode ser anulado de graça: o alvo que não
+        // reporta sai do jogo seja qual for a política, e a vez passa a partir
+        // do atirador. Quem deixou esgotar o prazo nunca fica com a vez
+        let (from, forfeit) = match &self.phase {
+            Phase::InProgress(Turn::AwaitingReport { shooter, .. }) => (shooter.clone(), true),
+            _ => (stalled.to_string(), forfeit),
+        };
+        if forfeit {
+            self.pmap.get_mut(stalled).unwrap().forfeited = true;
+        }
+        let in_play = self.in_play();
+        self.phase = if in_play.len() == 1 {
+            Phase::InProgress(Turn::AwaitingWin {
+                player: in_play[0].clone(),
+            })
+        } else {
+            let next = self.pick_next(&from, false);
+            Phase::InProgress(Turn::AwaitingFire { player: next })
+        };
+    }
+
+    // Fleets still playing, in join order
+    fn in_play(&self) -> Vec<String> {
+        self.order
+            .iter()
+            .filter(|f| !self.pmap[*f].out())
+            .cloned()
+            .collect()
+    }
+
+    fn stalled_turn(&self) -> Result<String, GameError> {
+        match &self.phase {
+            Phase::Lobby => Err(GameError::GameNotStarted),
+            Phase::Finished { .. } => Err(GameError::GameFinished),
+            Phase::Cancelled => Err(GameError::GameCancelled),
+            // A última frota não tem prazo para reclamar a vitória
+            Phase::InProgress(Turn::AwaitingWin { .. }) => Err(GameError::NotTimedOut),
+            Phase::InProgress(_) => Ok(self.stalled_player().unwrap().clone()),
+        }
+    }
+
+    fn check_lobby(&self) -> Result<(), GameError> {
+        match self.phase {
+            Phase::Lobby => Ok(()),
+            Phase::InProgress(_) => Err(GameError::GameStarted),
+            Phase::Finished { .. } => Err(GameError::GameFinished),
+            Phase::Cancelled => Err(GameError::GameCancelled),
+        }
+    }
+
+    fn check_turn(&self, fleetid: &str) -> Result<(), GameError> {
+        match &self.phase {
+            Phase::Lobby => Err(GameError::GameNotStarted),
+            Phase::Finished { .. } => Err(GameError::GameFinished),
+            Phase::Cancelled => Err(GameError::GameCancelled),
+            Phase::InProgress(Turn::AwaitingReport { .. }) => Err(GameError::ReportPending),
+            Phase::InProgress(Turn::AwaitingWin { .. }) => Err(GameError::WinPending),
+            _ if self.next_player().map(String::as_str) != Some(fleetid) => {
+                Err(GameError::NotYourTurn)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Each player's commands must come with consecutive sequence numbers, so a
+    // receipt that was already used (or one from the future) is refused
+    fn check_seq(&self, fleetid: &str, seq: u32) -> Result<(), GameError> {
+        match self.pmap.get(fleetid) {
+            Some(player) if player.seq.checked_add(1) == Some(seq) => Ok(()),
+            Some(player) => Err(GameError::OutOfSequence {
+                expected: player.seq.saturating_add(1),
+                got: seq,
+            }),
+            None => Err(GameError::UnknownPlayer(fleetid.to_string())),
+        }
+    }
+
+    fn check_board(&self, fleetid: &str, board: Digest) -> Result<(), GameError> {
+        match self.pmap.get(fleetid) {
+            Some(player) if player.current_state == board => Ok(()),
+            Some(_) => Err(GameError::BoardMismatch),
+            None => Err(GameError::UnknownPlayer(fleetid.to_string())),
+        }
+    }
+
+    // Definir o próximo jogador segundo a política de turnos; hit diz se a
+    // jogada que termina foi um tiro acertado. Frotas fora do jogo são saltadas
+    fn pick_next(&mut self, current: &str, hit: bool) -> String {
+        let in_play = self.in_play();
+        match self.turns {
+            TurnPolicy::ShooterAgainOnHit if hit => current.to_string(),
+            TurnPolicy::RoundRobin | TurnPolicy::ShooterAgainOnHit => {
+                let i = self.order.iter().position(|f| f == current).unwrap_or(0);
+                (1..=self.order.len())
+                    .map(|step| &self.order[(i + step) % self.order.len()])
+                    .find(|f| in_play.contains(f))
+                    .unwrap_or(&current.to_string())
+                    .clone()
+            }
+            // Sorteio verificável por quem conheça a semente publicada no
+            // GameStarted: candidatos por ordem de join, índice next_u64 % n
+            // de um ChaCha8Rng::seed_from_u64(seed)
+            TurnPolicy::SeededRandom => {
+                let candidates: Vec<&String> = in_play.iter().filter(|f| *f != current).collect();
+                if candidates.is_empty() {
+                    return current.to_string();
+                }
+                let i = (self.rng.next_u64() % candidates.len() as u64) as usize;
+                candidates[i].clone()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Compromisso de board fictício; o jogo só compara digests
+    fn board(n: u32) -> Digest {
+        Digest::from([n; 8])
+    }
+
+    fn rules(ship_sizes: Vec<usize>) -> GameRules {
+        GameRules {
+            width: 4,
+            height: 4,
+            ship_sizes,
+            no_touching: false,
+        }
+    }
+
+    // Jogo no lobby com os jogadores dados; o jogador i compromete board(i + 1)
+    fn lobby(players: &[&str], ship_sizes: Vec<usize>, timeout: Option<TurnTimeout>) -> Game {
+        let rules = rules(ship_sizes);
+        let mut game = Game::new(
+            rules.clone(),
+            7,
+            2,
+            players.len() as u32,
+            [0; 32],
+            TurnPolicy::RoundRobin,
+            timeout,
+        );
+        for (i, fleetid) in players.iter().enumerate() {
+            game.join(fleetid, 0, board(i as u32 + 1), &rules, [0; 32])
+                .unwrap();
+        }
+        game
+    }
+
+    fn started(players: &[&str], ship_sizes: Vec<usize>, timeout: Option<TurnTimeout>) -> Game {
+        let mut game = lobby(players, ship_sizes, timeout);
+        game.start().unwrap();
+        game
+    }
+
+    fn awaiting_fire(player: &str) -> Phase {
+        Phase::InProgress(Turn::AwaitingFire {
+            player: player.to_string(),
+        })
+    }
+
+    // O que uma transição recusada não pode mudar: a fase e, por jogador,
+    // os tiros, o seq e o board comprometido
+    type Snapshot = (Phase, Vec<(String, Vec<u8>, u32, Digest)>);
+
+    fn snapshot(game: &Game) -> Snapshot {
+        let players = game
+            .order
+            .iter()
+            .map(|f| {
+                let p = &game.pmap[f];
+                (f.clone(), p.shots.clone(), p.seq, p.current_state)
+            })
+            .collect();
+        (game.phase.clone(), players)
+    }
+
+    fn rejects<T: fmt::Debug + PartialEq>(
+        game: &mut Game,
+        error: GameError,
+        command: impl FnOnce(&mut Game) -> Result<T, GameError>,
+    ) {
+        let before = snapshot(game);
+        assert_eq!(command(game), Err(error));
+        assert_eq!(snapshot(game), before);
+    }
+
+    #[test]
+    fn start_moves_lobby_to_first_joined_player() {
+        let mut game = lobby(&["a", "b"], vec![2], None);
+        game.start().unwrap();
+        assert_eq!(game.phase, awaiting_fire("a"));
+    }
+
+    #[test]
+    fn cancel_moves_lobby_to_cancelled() {
+        let mut game = lobby(&["a", "b"], vec![2], None);
+        game.cancel().unwrap();
+        assert_eq!(game.phase, Phase::Cancelled);
+    }
+
+    #[test]
+    fn fire_awaits_report_from_target() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        let pos = Coord::new(1, 2);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        assert_eq!(
+            game.phase,
+            Phase::InProgress(Turn::AwaitingReport {
+                shooter: "a".to_string(),
+                target: "b".to_string(),
+                pos,
+            })
+        );
+        assert_eq!(game.pmap["a"].seq, 1);
+        assert_eq!(game.pmap["b"].shots[pos.index(4)], 1);
+    }
+
+    #[test]
+    fn wave_passes_turn_without_firing() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        game.wave("a", 1, board(1)).unwrap();
+        assert_eq!(game.phase, awaiting_fire("b"));
+        assert_eq!(game.pmap["a"].seq, 1);
+    }
+
+    #[test]
+    fn report_passes_turn() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        let pos = Coord::new(0, 0);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        game.report("b", 1, board(2), board(12), pos, true).unwrap();
+        assert_eq!(game.phase, awaiting_fire("b"));
+        let b = &game.pmap["b"];
+        assert_eq!((b.shots[0], b.hit_count, b.seq), (2, 1, 1));
+        assert_eq!(b.current_state, board(12));
+    }
+
+    fn awaiting_win(player: &str) -> Phase {
+        Phase::InProgress(Turn::AwaitingWin {
+            player: player.to_string(),
+        })
+    }
+
+    // a afunda a única frota de b, que tem um só barco de uma célula
+    fn sunk(players: &[&str]) -> Game {
+        let mut game = started(players, vec![1], None);
+        let pos = Coord::new(3, 3);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        game.report("b", 1, board(2), board(12), pos, true).unwrap();
+        game
+    }
+
+    #[test]
+    fn report_sinking_last_other_fleet_awaits_win() {
+        let game = sunk(&["a", "b"]);
+        assert_eq!(game.phase, awaiting_win("a"));
+    }
+
+    #[test]
+    fn forfeit_timeout_awaits_win_of_last_player() {
+        let timeout = TurnTimeout {
+            seconds: 10,
+            policy: TimeoutPolicy::Forfeit,
+        };
+        let mut game = started(&["a", "b"], vec![2], Some(timeout));
+        game.restart_clock(100);
+        assert_eq!(game.time_out(110), Ok("a".to_string()));
+        assert_eq!(game.phase, awaiting_win("b"));
+        // A última frota não tem prazo
+        assert_eq!(game.deadline(), None);
+    }
+
+    #[test]
+    fn win_of_last_fleet_finishes() {
+        let mut game = sunk(&["a", "b"]);
+        game.win("a", 2, board(1)).unwrap();
+        assert_eq!(
+            game.phase,
+            Phase::Finished {
+                winner: "a".to_string()
+            }
+        );
+        assert_eq!(game.pmap["a"].seq, 2);
+    }
+
+    #[test]
+    fn rejects_win_with_fleets_afloat() {
+        let mut game = sunk(&["a", "b", "c"]);
+        assert_eq!(game.phase, awaiting_fire("c"));
+        rejects(
+            &mut game,
+            GameError::FleetsAfloat(vec!["c".to_string()]),
+            |g| g.win("a", 2, board(1)),
+        );
+    }
+
+    #[test]
+    fn rejects_fire_once_win_is_pending() {
+        let mut game = sunk(&["a", "b"]);
+        rejects(&mut game, GameError::WinPending, |g| {
+            g.fire("a", 2, board(1), "b", Coord::new(0, 0))
+        });
+    }
+
+    #[test]
+    fn rejects_fire_before_start() {
+        let mut game = lobby(&["a", "b"], vec![2], None);
+        rejects(&mut game, GameError::GameNotStarted, |g| {
+            g.fire("a", 1, board(1), "b", Coord::new(0, 0))
+        });
+    }
+
+    #[test]
+    fn rejects_fire_with_report_pending() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        game.fire("a", 1, board(1), "b", Coord::new(0, 0)).unwrap();
+        rejects(&mut game, GameError::ReportPending, |g| {
+            g.fire("b", 1, board(2), "a", Coord::new(0, 0))
+        });
+    }
+
+    #[test]
+    fn rejects_report_without_shot() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        rejects(&mut game, GameError::NoShotPending, |g| {
+            g.report("b", 1, board(2), board(12), Coord::new(0, 0), false)
+        });
+    }
+
+    #[test]
+    fn rejects_report_by_shooter() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        let pos = Coord::new(0, 0);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        rejects(&mut game, GameError::NotTarget, |g| {
+            g.report("a", 2, board(1), board(11), pos, false)
+        });
+    }
+
+    #[test]
+    fn rejects_report_of_other_position() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        let pos = Coord::new(0, 0);
+        let other = Coord::new(1, 1);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        rejects(
+            &mut game,
+            GameError::ReportMismatch {
+                expected: pos,
+                got: other,
+            },
+            |g| g.report("b", 1, board(2), board(12), other, false),
+        );
+    }
+
+    #[test]
+    fn rejects_fire_from_uncommitted_board() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        rejects(&mut game, GameError::BoardMismatch, |g| {
+            g.fire("a", 1, board(9), "b", Coord::new(0, 0))
+        });
+    }
+
+    #[test]
+    fn rejects_out_of_sequence_command() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        rejects(
+            &mut game,
+            GameError::OutOfSequence {
+                expected: 1,
+                got: 2,
+            },
+            |g| g.fire("a", 2, board(1), "b", Coord::new(0, 0)),
+        );
+    }
+
+    #[test]
+    fn rejects_join_of_full_game() {
+        let mut game = lobby(&["a", "b"], vec![2], None);
+        let rules = game.rules.clone();
+        rejects(&mut game, GameError::GameFull(2), |g| {
+            g.join("c", 0, board(3), &rules, [0; 32])
+        });
+    }
+
+    #[test]
+    fn rejects_join_with_different_rules() {
+        let mut game = lobby(&["a"], vec![2], None);
+        game.max_players = 3;
+        rejects(&mut game, GameError::DifferentRules, |g| {
+            g.join("b", 0, board(2), &GameRules::standard(), [0; 32])
+        });
+    }
+
+    #[test]
+    fn rejects_fire_at_own_fleet() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        rejects(&mut game, GameError::SelfTarget, |g| {
+            g.fire("a", 1, board(1), "a", Coord::new(0, 0))
+        });
+    }
+
+    // a dispara sobre b em pos, b reporta e devolve o tiro sobre a, que falha;
+    // volta a ser a vez de a, com o seq 3
+    fn fired_back(hit: bool) -> Game {
+        let mut game = started(&["a", "b"], vec![2], None);
+        let pos = Coord::new(2, 1);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        game.report("b", 1, board(2), board(12), pos, hit).unwrap();
+        game.fire("b", 2, board(12), "a", pos).unwrap();
+        game.report("a", 2, board(1), board(1), pos, false).unwrap();
+        assert_eq!(game.phase, awaiting_fire("a"));
+        game
+    }
+
+    #[test]
+    fn rejects_second_shot_at_missed_cell() {
+        let mut game = fired_back(false);
+        let pos = Coord::new(2, 1);
+        assert_eq!(game.pmap["b"].shots[pos.index(4)], 1);
+        rejects(&mut game, GameError::AlreadyFired(pos), |g| {
+            g.fire("a", 3, board(1), "b", pos)
+        });
+    }
+
+    #[test]
+    fn rejects_second_shot_at_hit_cell() {
+        let mut game = fired_back(true);
+        let pos = Coord::new(2, 1);
+        assert_eq!(game.pmap["b"].shots[pos.index(4)], 2);
+        rejects(&mut game, GameError::AlreadyFired(pos), |g| {
+            g.fire("a", 3, board(1), "b", pos)
+        });
+    }
+
+    #[test]
+    fn rejects_fire_at_unknown_fleet() {
+        let mut game = started(&["a", "b"], vec![2], None);
+        rejects(&mut game, GameError::UnknownTarget("z".to_string()), |g| {
+            g.fire("a", 1, board(1), "z", Coord::new(0, 0))
+        });
+    }
+
+    #[test]
+    fn rejects_fire_at_eliminated_fleet() {
+        let mut game = started(&["a", "b", "c"], vec![1], None);
+        let pos = Coord::new(0, 0);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        game.report("b", 1, board(2), board(12), pos, true).unwrap();
+        // b saiu do jogo e a vez salta para c
+        assert!(game.pmap["b"].eliminated());
+        assert_eq!(game.phase, awaiting_fire("c"));
+        rejects(&mut game, GameError::FleetOut("b".to_string()), |g| {
+            g.fire("c", 1, board(3), "b", Coord::new(1, 1))
+        });
+    }
+
+    #[test]
+    fn rejects_report_by_other_player() {
+        let mut game = started(&["a", "b", "c"], vec![2], None);
+        let pos = Coord::new(0, 0);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        rejects(&mut game, GameError::NotTarget, |g| {
+            g.report("c", 1, board(3), board(13), pos, false)
+        });
+    }
+
+    fn pass() -> Option<TurnTimeout> {
+        Some(TurnTimeout {
+            seconds: 10,
+            policy: TimeoutPolicy::Pass,
+        })
+    }
+
+    #[test]
+    fn unreported_shot_takes_target_out_under_pass() {
+        let mut game = started(&["a", "b", "c"], vec![2], pass());
+        let pos = Coord::new(3, 0);
+        game.restart_clock(100);
+        game.fire("a", 1, board(1), "b", pos).unwrap();
+        assert_eq!(game.time_out(110), Ok("b".to_string()));
+        // O tiro fica registado e a vez salta o alvo que não reportou
+        assert!(game.pmap["b"].forfeited);
+        assert_eq!(game.pmap["b"].shots[pos.index(4)], 1);
+        assert_eq!(game.phase, awaiting_fire("c"));
+    }
+
+    #[test]
+    fn pass_timeout_gives_turn_to_another_player() {
+        let mut game = started(&["a", "b"], vec![2], pass());
+        game.restart_clock(100);
+        assert_eq!(game.time_out(110), Ok("a".to_string()));
+        assert!(!game.pmap["a"].forfeited);
+        assert_eq!(game.phase, awaiting_fire("b"));
+    }
+
+    #[test]
+    fn deadline_saturates_instead_of_overflowing() {
+        let timeout = TurnTimeout {
+            seconds: u64::MAX,
+            policy: TimeoutPolicy::Forfeit,
+        };
+        let mut game = started(&["a", "b"], vec![2], Some(timeout));
+        game.restart_clock(100);
+        assert_eq!(game.deadline(), Some(u64::MAX));
+        assert!(!game.expired(101));
+    }
+}
@@ -0,0 +1,166 @@
+// Hash-linked block ledger. Every accepted command becomes a transaction in a
+// new block that points to the hash of the previous one, so rewriting any past
+// command breaks every block after it.
+
+use risc0_zkvm::{Digest, Receipt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fleetcore::{Command, LobbyCommand};
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Transaction {
+    pub cmd: Command,
+    pub journal_digest: Digest, // SHA-256 of the receipt's journal, or of the lobby command
+}
+
+impl Transaction {
+    pub fn new(cmd: Command, receipt: &Receipt) -> Self {
+        Transaction {
+            cmd,
+            journal_digest: journal_digest(receipt),
+        }
+    }
+
+    pub fn lobby(command: &LobbyCommand) -> Self {
+        Transaction {
+            cmd: command.action.cmd(),
+            journal_digest: Digest::from(command.digest()),
+        }
+    }
+
+    // Timeout applied by the chain itself to a stalled game
+    pub fn timeout(gameid: &str) -> Self {
+        Transaction {
+            cmd: Command::Timeout,
+            journal_digest: Digest::try_from(Sha256::digest(gameid.as_bytes()).as_slice()).unwrap(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Block {
+    pub height: u64,
+    pub prev_hash: Digest,
+    pub timestamp: u64, // seconds since the UNIX epoch
+    pub transactions: Vec<Transaction>,
+    pub hash: Digest,
+}
+
+impl Block {
+    // Fixed first block, identical on every chain
+    pub fn genesis() -> Self {
+        let mut block = Block {
+            height: 0,
+            prev_hash: Digest::ZERO,
+            timestamp: 0,
+            transactions: Vec::new(),
+            hash: Digest::ZERO,
+        };
+        block.hash = block.compute_hash();
+        block
+    }
+
+    // SHA-256 over every field of the block but the hash itself
+    pub fn compute_hash(&self) -> Digest {
+        let contents = serde_json::to_vec(&(
+            self.height,
+            &self.prev_hash,
+            self.timestamp,
+            &self.transactions,
+        ))
+        .unwrap();
+        Digest::try_from(Sha256::digest(&contents).as_slice()).unwrap()
+    }
+}
+
+pub fn journal_digest(receipt: &Receipt) -> Digest {
+    Digest::try_from(Sha256::digest(&receipt.journal.bytes).as_slice()).unwrap()
+}
+
+pub struct Ledger {
+    blocks: Vec<Block>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Ledger {
+            blocks: vec![Block::genesis()],
+        }
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    // Height the next block will have
+    pub fn next_height(&self) -> u64 {
+        self.blocks.last().unwrap().height + 1
+    }
+
+    // Timestamp the next block will have: now, but never before the last block.
+    // Commands are applied at this time, so a replay sees the same clock
+    pub fn next_timestamp(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+            .max(self.blocks.last().unwrap().timestamp)
+    }
+
+    // Builds the next block on top of the last one, without appending it
+    pub fn next_block(&self, transactions: Vec<Transaction>, timestamp: u64) -> Block {
+        let last = self.blocks.last().unwrap();
+        let mut block = Block {
+            height: last.height + 1,
+            prev_hash: last.hash,
+            timestamp,
+            transactions,
+            hash: Digest::ZERO,
+        };
+        block.hash = block.compute_hash();
+        block
+    }
+
+    // Appends a block, checking that it extends the current chain
+    pub fn push(&mut self, block: Block) -> Result<(), String> {
+        check_link(self.blocks.last().unwrap(), &block)?;
+        self.blocks.push(block);
+        Ok(())
+    }
+
+    // Re-validates the whole chain from genesis
+    pub fn verify(&self) -> Result<(), String> {
+        match self.blocks.first() {
+            Some(first) if first.hash == Block::genesis().hash => {}
+            _ => return Err("Chain does not start with the genesis block".to_string()),
+        }
+        for pair in self.blocks.windows(2) {
+            check_link(&pair[0], &pair[1])?;
+        }
+        Ok(())
+    }
+}
+
+fn check_link(prev: &Block, block: &Block) -> Result<(), String> {
+    if block.height != prev.height + 1 {
+        return Err(format!(
+            "Block {} follows block {}",
+            block.height, prev.height
+        ));
+    }
+    if block.prev_hash != prev.hash {
+        return Err(format!(
+            "Block {} does not point to the hash of block {}",
+            block.height, prev.height
+        ));
+    }
+    if block.timestamp < prev.timestamp {
+        return Err(format!("Block {} goes back in time", block.height));
+    }
+    if block.hash != block.compute_hash() {
+        return Err(format!("Block {} has an invalid hash", block.height));
+    }
+    Ok(())
+}
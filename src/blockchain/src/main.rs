@@ -4,58 +4,117 @@
 #![allow(dead_code)]
 
 use axum::{
-    extract::Extension,
+    extract::{Extension, Path, Query},
+    http::{HeaderMap, StatusCode},
     response::{sse::Event, Html, IntoResponse},
     routing::{get, post},
     Json, Router,
 };
-use futures::stream::StreamExt;
-use rand::{seq::IteratorRandom, SeedableRng};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use futures::{future, stream::StreamExt};
+use rand::{seq::IteratorRandom, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use risc0_zkvm::Digest;
+use serde::{de::DeserializeOwned, Deserialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::Infallible,
     error::Error,
     net::SocketAddr,
     sync::{Arc, Mutex},
 };
-use tokio::sync::broadcast;
-use tokio_stream::wrappers::BroadcastStream;
 
-use fleetcore::{BaseJournal, Command, CommunicationData, FireJournal, ReportJournal};
+use fleetcore::{
+    BaseJournal, ChainEvent, ChainResponse, ChainVerification, Command, CommunicationData, Coord,
+    ErrorCode, EventKind, FireJournal, GameRules, GameState, HistoryEntry, JoinJournal,
+    JournalHeader, LobbyAction, LobbyCommand, Phase, ReportJournal, TimeoutPolicy, Turn,
+    TurnTimeout, PROTOCOL_VERSION,
+};
 use methods::{FIRE_ID, JOIN_ID, REPORT_ID, WAVE_ID, WIN_ID};
 
-struct Player {
-    name: String,
-    current_state: Digest,
-    // criar tabela para cada jogador para ter shots
-    shots: [u8; 100], // 0 = sem tiro, 1 = falha, 2 = acerto
-    hit_count: u32,   // 👈 new field
-}
+mod events;
+mod game;
+mod ledger;
+mod store;
 
-struct Game {
-    pmap: HashMap<String, Player>,
-    next_player: Option<String>,
-    next_report: Option<String>,
-    //current_shot: Option<u8>, // (position_index)
-    current_shot: Option<(u8, String)>, // (position_index, target_player_id)
-}
+use events::EventLog;
+use game::{Game, GameError};
+use ledger::{journal_digest, Ledger, Transaction};
+use store::{Record, Store};
 
 #[derive(Clone)]
 struct SharedData {
-    tx: broadcast::Sender<String>,
+    events: Arc<Mutex<EventLog>>,
     gmap: Arc<Mutex<HashMap<String, Game>>>,
-    rng: Arc<Mutex<rand::rngs::StdRng>>,
+    rng: Arc<Mutex<ChaCha8Rng>>, // algoritmo fixo, o replay deriva as mesmas sementes
+    store: Arc<Mutex<Option<Store>>>, // None when auditing a log with verify-chain
+    ledger: Arc<Mutex<Ledger>>,
+    accepted: Arc<Mutex<HashSet<Digest>>>, // journal digests of every accepted receipt
+}
+
+impl SharedData {
+    fn new(seed: u64, store: Option<Store>) -> Self {
+        SharedData {
+            events: Arc::new(Mutex::new(EventLog::new())),
+            gmap: Arc::new(Mutex::new(HashMap::new())),
+            rng: Arc::new(Mutex::new(ChaCha8Rng::seed_from_u64(seed))),
+            store: Arc::new(Mutex::new(store)),
+            ledger: Arc::new(Mutex::new(Ledger::new())),
+            accepted: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+}
+
+fn log_path() -> String {
+    std::env::var("CHAIN_LOG").unwrap_or("chain.log".to_string())
 }
 
+// Usage:
+//   blockchain                      run the chain server
+//   blockchain verify-chain [log]   audit a transaction log and exit
 #[tokio::main]
 async fn main() {
-    // Create a broadcast channel for log messages
-    let (tx, _rx) = broadcast::channel::<String>(100);
-    let shared = SharedData {
-        tx: tx,
-        gmap: Arc::new(Mutex::new(HashMap::new())),
-        rng: Arc::new(Mutex::new(rand::rngs::StdRng::from_entropy())),
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("verify-chain") {
+        let path = args.get(2).cloned().unwrap_or_else(log_path);
+        if let Err(e) = verify_chain(&path) {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Reopen the transaction log, starting a new one with a fresh rng seed
+    let path = log_path();
+    let (mut store, records) = match Store::open(&path) {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("❌ Could not open the chain log {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let seed = match records.first() {
+        Some(Record::Genesis { seed }) => *seed,
+        Some(_) => {
+            eprintln!("❌ {} does not start with a genesis record", path);
+            std::process::exit(1);
+        }
+        None => {
+            let seed = rand::rngs::StdRng::from_entropy().gen();
+            store
+                .append(&Record::Genesis { seed })
+                .expect("failed to write the chain log");
+            seed
+        }
     };
+    let shared = SharedData::new(seed, Some(store));
+
+    // Replay the accepted commands to rebuild the games and the ledger
+    let replayed = replay(&shared, records).expect("the chain log does not replay");
+    println!("Replayed {} commands from {}", replayed, path);
+
+    // Turn deadlines are enforced in the background
+    tokio::spawn(watch_deadlines(shared.clone()));
 
     // Build our application with a route
 
@@ -63,6 +122,13 @@ async fn main() {
         .route("/", get(index))
         .route("/logs", get(logs))
         .route("/chain", post(smart_contract))
+        .route("/lobby", post(lobby))
+        .route("/blocks", get(blocks))
+        .route("/blocks/verify", get(verify_blocks))
+        .route("/blocks/:height", get(block))
+        .route("/games", get(games))
+        .route("/games/:id", get(game))
+        .route("/games/:id/history", get(game_history))
         .layer(Extension(shared));
 
     // Run our app with hyper
@@ -74,6 +140,99 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+// Runs the accepted commands of a log through the handlers again, in order.
+// Every receipt is verified again and every command must still be accepted,
+// otherwise the log and the game rules have diverged.
+fn replay(shared: &SharedData, records: Vec<Record>) -> Result<usize, String> {
+    let mut replayed = 0;
+    for record in records {
+        let (block, transaction, outcome) = match record {
+            Record::Genesis { .. } => continue,
+            Record::Accepted { block, data } => {
+                let transaction = Transaction::new(data.cmd, &data.receipt);
+                let outcome = apply_command(shared, &data, block.height, block.timestamp);
+                (block, transaction, outcome)
+            }
+            Record::Lobby { block, command } => {
+                let transaction = Transaction::lobby(&command);
+                let outcome = apply_lobby(shared, &command, block.height, block.timestamp);
+                (block, transaction, outcome)
+            }
+            Record::Timeout { block, gameid } => {
+                let transaction = Transaction::timeout(&gameid);
+                let outcome = apply_timeout(shared, &gameid, block.height, block.timestamp);
+                (block, transaction, outcome)
+            }
+        };
+        let recorded = block
+            .transactions
+            .iter()
+            .any(|tx| tx.cmd == transaction.cmd && tx.journal_digest == transaction.journal_digest);
+        if !recorded {
+            return Err(format!(
+                "Block {} does not record its {:?} command",
+                block.height, transaction.cmd
+            ));
+        }
+        if let Err(ChainResponse::Rejected { message, .. }) = outcome {
+            return Err(format!(
+                "Block {}: {:?} command rejected on replay: {}",
+                block.height, transaction.cmd, message
+            ));
+        }
+        shared.ledger.lock().unwrap().push(block)?;
+        replayed += 1;
+    }
+    Ok(replayed)
+}
+
+// Independent audit of a transaction log: re-verifies every receipt against the
+// image IDs, re-runs the games and the ledger from genesis and prints the result
+fn verify_chain(path: &str) -> Result<(), String> {
+    let records = Store::load(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let seed = match records.first() {
+        Some(Record::Genesis { seed }) => *seed,
+        _ => return Err(format!("{} does not start with a genesis record", path)),
+    };
+
+    let shared = SharedData::new(seed, None);
+    let replayed = replay(&shared, records)?;
+    let ledger = shared.ledger.lock().unwrap();
+    ledger.verify()?;
+
+    println!(
+        "✅ {} commands in {} blocks verified",
+        replayed,
+        ledger.blocks().len() - 1
+    );
+    let gmap = shared.gmap.lock().unwrap();
+    let mut gameids: Vec<&String> = gmap.keys().collect();
+    gameids.sort();
+    for gameid in gameids {
+        let game = &gmap[gameid];
+        println!(
+            "🎮 Game {}: next player {:?}, winner {:?}",
+            gameid,
+            game.next_player(),
+            game.winner()
+        );
+        for (fleetid, player) in &game.pmap {
+            let status = if player.eliminated() {
+                " (eliminated)"
+            } else if player.forfeited {
+                " (forfeited)"
+            } else {
+                ""
+            };
+            println!(
+                "   🚢 {} with {} hits taken{}",
+                fleetid, player.hit_count, status
+            );
+        }
+    }
+    Ok(())
+}
+
 // Handler to serve the HTML page
 async fn index() -> Html<&'static str> {
     Html(
@@ -87,11 +246,34 @@ async fn index() -> Html<&'static str> {
             <h1>Registered Transactions</h1>          
             <ul id="logs"></ul>
             <script>
-                const eventSource = new EventSource('/logs');
+                // /?game=<id>&fleet=<id> shows only the events of that game or fleet
+                const eventSource = new EventSource('/logs' + window.location.search);
+                const pos = p => String.fromCharCode(65 + p.x) + p.y;
+                function describe(e) {
+                    // Unit variants such as GameCancelled serialize as a plain string
+                    const [kind, d] = typeof e.kind === 'string' ? [e.kind, {}] : Object.entries(e.kind)[0];
+                    const game = e.gameid ? `🎮 [Game ${e.gameid}] ` : '';
+                    switch (kind) {
+                        case 'GameCreated': return `${game}🆕 Game created for ${d.min_players} to ${d.max_players} players`;
+                        case 'GameStarted': return `${game}🏁 Game started, turn order: ${d.order.join(', ')}`;
+                        case 'GameCancelled': return `${game}🚫 Game cancelled`;
+                        case 'PlayerJoined': return `${game}🚀 Player with fleet ID ${d.fleetid} joined`;
+                        case 'ShotFired': return `${game}🔫 Player ${d.shooter} shot on position ${pos(d.pos)} of Player ${d.target}`;
+                        case 'ShotReported': return `${game}Player ${d.fleetid} ${d.hit ? '💥 Hit confirmed' : '💨 Missed shot'} at ${pos(d.pos)}`;
+                        case 'TurnPassed': return `${game}👉 Next to fire: ${d.next}`;
+                        case 'TurnTimedOut': return `${game}⏰ Player ${d.fleetid} let the turn time out (${d.policy})`;
+                        case 'WinPending': return `${game}🏅 Fleet ${d.fleetid} is the last afloat and may claim the win`;
+                        case 'FleetEliminated': return `${game}☠️ Fleet ${d.fleetid} was eliminated`;
+                        case 'PlayerForfeited': return `${game}🏳️ Player ${d.fleetid} forfeited`;
+                        case 'GameWon': return `${game}🏆 Player ${d.winner} won the game!`;
+                        case 'ReceiptRejected': return `${game}❌ ${d.cmd} by ${d.fleetid ?? 'unknown fleet'} rejected: ${d.message} (${d.code})`;
+                        default: return JSON.stringify(e);
+                    }
+                }
                 eventSource.onmessage = function(event) {
                     const logs = document.getElementById('logs');
                     const log = document.createElement('li');
-                    log.textContent = event.data;
+                    log.textContent = describe(JSON.parse(event.data));
                     logs.appendChild(log);
                 };
             </script>
@@ -101,362 +283,789 @@ async fn index() -> Html<&'static str> {
     )
 }
 
-// Handler to manage SSE connections
+#[derive(Deserialize)]
+struct EventFilter {
+    game: Option<String>,
+    fleet: Option<String>,
+    since: Option<u64>, // id of the last event seen, like the Last-Event-ID header
+}
+
+impl EventFilter {
+    fn matches(&self, event: &ChainEvent) -> bool {
+        let fleet = match &self.fleet {
+            Some(fleet) => event.involves(fleet),
+            None => true,
+        };
+        (self.game.is_none() || event.gameid == self.game) && fleet
+    }
+}
+
+// Handler to manage SSE connections: /logs?game=<id>&fleet=<id>. A new client
+// first gets the journal of recent events for what it watches; one that
+// reconnects (EventSource sends Last-Event-ID) gets only the events it missed.
 #[axum::debug_handler]
-async fn logs(Extension(shared): Extension<SharedData>) -> impl IntoResponse {
-    let rx = BroadcastStream::new(shared.tx.subscribe());
-    let stream = rx.filter_map(|result| async move {
-        match result {
-            Ok(msg) => Some(Ok(Event::default().data(msg))),
-            Err(_) => Some(Err(Box::<dyn Error + Send + Sync>::from("Error"))),
+async fn logs(
+    Extension(shared): Extension<SharedData>,
+    Query(filter): Query<EventFilter>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .or(filter.since);
+
+    let stream = events::subscribe(shared.events.clone(), last_id)
+        .filter(move |event| future::ready(filter.matches(event)))
+        .map(|event| {
+            Ok::<_, Infallible>(
+                Event::default()
+                    .id(event.id.to_string())
+                    .json_data(&event)
+                    .unwrap(),
+            )
+        });
+
+    axum::response::sse::Sse::new(stream)
+}
+
+async fn smart_contract(
+    Extension(shared): Extension<SharedData>,
+    Json(input_data): Json<CommunicationData>,
+) -> Json<ChainResponse> {
+    // The log stays locked while the command runs so that it records the
+    // commands in the order they were applied
+    let mut store = shared.store.lock().unwrap();
+    let (height, now) = next_block_at(&shared);
+    let gameid = match apply_command(&shared, &input_data, height, now) {
+        Ok(gameid) => gameid,
+        Err(rejected) => return Json(rejected),
+    };
+
+    let transaction = Transaction::new(input_data.cmd, &input_data.receipt);
+    commit(&shared, &mut store, transaction, now, |block| {
+        Record::Accepted {
+            block,
+            data: input_data,
         }
     });
+    Json(accepted(&shared, height, &gameid))
+}
 
-    axum::response::sse::Sse::new(stream)
+// Handler for the lobby operations, signed by the owner of the game
+async fn lobby(
+    Extension(shared): Extension<SharedData>,
+    Json(command): Json<LobbyCommand>,
+) -> Json<ChainResponse> {
+    let mut store = shared.store.lock().unwrap();
+    let (height, now) = next_block_at(&shared);
+    let gameid = match apply_lobby(&shared, &command, height, now) {
+        Ok(gameid) => gameid,
+        Err(rejected) => return Json(rejected),
+    };
+
+    let transaction = Transaction::lobby(&command);
+    commit(&shared, &mut store, transaction, now, |block| {
+        Record::Lobby { block, command }
+    });
+    Json(accepted(&shared, height, &gameid))
 }
 
-fn xy_pos(pos: u8) -> String {
-    let x = pos % 10;
-    let y = pos / 10;
-    format!("{}{}", (x + 65) as char, y)
+// Height and timestamp of the block that will record the next command
+fn next_block_at(shared: &SharedData) -> (u64, u64) {
+    let ledger = shared.ledger.lock().unwrap();
+    (ledger.next_height(), ledger.next_timestamp())
 }
 
-fn pos_str_to_index(pos: &str) -> Option<u8> {
-    if pos.len() < 2 {
-        return None;
+// Records an accepted command in a new block and in the log, before anyone is
+// told it was accepted
+fn commit(
+    shared: &SharedData,
+    store: &mut Option<Store>,
+    transaction: Transaction,
+    timestamp: u64,
+    record: impl FnOnce(ledger::Block) -> Record,
+) {
+    let mut ledger = shared.ledger.lock().unwrap();
+    let block = ledger.next_block(vec![transaction], timestamp);
+    let record = record(block.clone());
+    if let Some(Err(e)) = store.as_mut().map(|store| store.append(&record)) {
+        // O jogo em memória já mudou mas o comando não está no disco: nada é
+        // respondido, a chain pára e o replay do log repõe o que foi gravado
+        eprintln!("❌ Failed to write the chain log, stopping: {}", e);
+        std::process::exit(1);
     }
+    ledger.push(block).unwrap();
+}
 
-    let col_char = pos.chars().next()?.to_ascii_uppercase();
-    let row_str = &pos[1..];
+// O cliente recebe o bloco e o estado em que o jogo ficou
+fn accepted(shared: &SharedData, height: u64, gameid: &str) -> ChainResponse {
+    let game = shared.gmap.lock().unwrap()[gameid].state(gameid);
+    ChainResponse::Accepted {
+        block: height,
+        game: Box::new(game),
+    }
+}
 
-    let x = (col_char as u8).checked_sub(b'A')?;
-    let y: u8 = row_str.parse().ok()?;
+// Handler to list every block of the ledger
+async fn blocks(Extension(shared): Extension<SharedData>) -> impl IntoResponse {
+    Json(shared.ledger.lock().unwrap().blocks().to_vec())
+}
 
-    if x < 10 && y < 10 {
-        Some(y * 10 + x)
-    } else {
-        None
+// Handler to fetch a single block by height
+async fn block(
+    Extension(shared): Extension<SharedData>,
+    Path(height): Path<usize>,
+) -> impl IntoResponse {
+    match shared.ledger.lock().unwrap().blocks().get(height) {
+        Some(block) => Json(block.clone()).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("Block {} not found", height)).into_response(),
     }
 }
 
-async fn smart_contract(
+// Handler that re-validates the ledger from genesis
+async fn verify_blocks(Extension(shared): Extension<SharedData>) -> impl IntoResponse {
+    let ledger = shared.ledger.lock().unwrap();
+    let height = ledger.next_height() - 1;
+    match ledger.verify() {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(ChainVerification {
+                valid: true,
+                height,
+                error: None,
+            }),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ChainVerification {
+                valid: false,
+                height,
+                error: Some(e),
+            }),
+        ),
+    }
+}
+
+// Game id of an accepted command, or the answer that rejects it
+type Outcome = Result<String, ChainResponse>;
+
+// Handler to list every game, sorted by id
+async fn games(Extension(shared): Extension<SharedData>) -> impl IntoResponse {
+    let gmap = shared.gmap.lock().unwrap();
+    let mut games: Vec<GameState> = gmap.iter().map(|(id, g)| g.state(id)).collect();
+    games.sort_by(|a, b| a.gameid.cmp(&b.gameid));
+    Json(games)
+}
+
+// Handler to fetch the public state of a game
+async fn game(
     Extension(shared): Extension<SharedData>,
-    Json(input_data): Json<CommunicationData>,
-) -> String {
-    match input_data.cmd {
-        Command::Join => handle_join(&shared, &input_data),
-        Command::Fire => handle_fire(&shared, &input_data),
-        Command::Report => handle_report(&shared, &input_data),
-        Command::Wave => handle_wave(&shared, &input_data),
-        Command::Win => handle_win(&shared, &input_data),
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match shared.gmap.lock().unwrap().get(&id) {
+        Some(game) => Json(game.state(&id)).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("Game {} not found", id)).into_response(),
     }
 }
 
-fn handle_join(shared: &SharedData, input_data: &CommunicationData) -> String {
-    if input_data.receipt.verify(JOIN_ID).is_err() {
-        shared
-            .tx
-            .send("Attempting to join game with invalid receipt".to_string())
-            .unwrap();
-        return "Could not verify receipt".to_string();
+// Handler to list the accepted commands of a game, in order
+async fn game_history(
+    Extension(shared): Extension<SharedData>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match shared.gmap.lock().unwrap().get(&id) {
+        Some(game) => Json(game.history.clone()).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("Game {} not found", id)).into_response(),
     }
-    let data: BaseJournal = input_data.receipt.journal.decode().unwrap();
-    let mut gmap = shared.gmap.lock().unwrap();
-    let game = gmap.entry(data.gameid.clone()).or_insert(Game {
-        pmap: HashMap::new(),
-        next_player: Some(data.fleet.clone()),
-        next_report: None,
-        current_shot: None, // initialize current_shot as None
-    });
-    let player_inserted = game
-        .pmap
-        .entry(data.fleet.clone())
-        .or_insert_with(|| Player {
-            name: data.fleetid.clone(), //estava fleet
-            current_state: data.board.clone(),
-            shots: [0; 100], // 👈 Initialize all shots to 0
-            hit_count: 0,    // 👈 initialize to 0
-        })
-        .current_state
-        == data.board;
-    let mesg = if player_inserted {
-        //format!("[Game {}] Player with fleet ID: {} joined", data.gameid, data.fleetid)
-        format!(
-            "🎮 [Game {}] 🚀 Player with fleet ID {} joined",
-            data.gameid, data.fleetid
-        )
-    } else {
-        format!(
-            "❌ Player {} already in game {} ",
-            data.fleetid, data.gameid
-        )
-    };
-    shared.tx.send(mesg).unwrap();
-    "OK".to_string()
 }
 
-fn handle_fire(shared: &SharedData, input_data: &CommunicationData) -> String {
-    if input_data.receipt.verify(FIRE_ID).is_err() {
-        let _ = shared
-            .tx
-            .send("Tentativa de disparo com receipt inválido".to_string());
-        return "Could not verify receipt".to_string();
+// block and now are the height and timestamp of the block that will record the command
+fn apply_command(
+    shared: &SharedData,
+    input_data: &CommunicationData,
+    block: u64,
+    now: u64,
+) -> Outcome {
+    shared.events.lock().unwrap().begin_block(block);
+    let outcome = run_command(shared, input_data, block, now);
+    if let Err(ChainResponse::Rejected { code, message }) = &outcome {
+        // O jornal pode nem ser válido: o evento leva quem ele diz ser
+        let origin: Option<JournalOrigin> = input_data.receipt.journal.decode().ok();
+        shared.events.lock().unwrap().emit_rejected(
+            origin.as_ref().map(|o| o.gameid.as_str()),
+            EventKind::ReceiptRejected {
+                cmd: input_data.cmd,
+                fleetid: origin.as_ref().map(|o| o.fleetid.clone()),
+                code: *code,
+                message: message.clone(),
+            },
+        );
     }
+    outcome
+}
 
-    let data: FireJournal = match input_data.receipt.journal.decode() {
-        Ok(d) => d,
-        Err(_) => {
-            let _ = shared
-                .tx
-                .send("Erro a decodificar o FireJournal".to_string());
-            return "Failed to decode journal".to_string();
-        }
-    };
+fn run_command(
+    shared: &SharedData,
+    input_data: &CommunicationData,
+    block: u64,
+    now: u64,
+) -> Outcome {
+    // Um receipt já aceite não pode ser submetido outra vez
+    let digest = journal_digest(&input_data.receipt);
+    if shared.accepted.lock().unwrap().contains(&digest) {
+        return Err(ChainResponse::rejected(
+            ErrorCode::DuplicateReceipt,
+            "Duplicate receipt: already accepted",
+        ));
+    }
 
-    // Trancar o mapa de jogos para alterar o estado
+    check_header(input_data)?;
+
+    let gameid = match input_data.cmd {
+        Command::Join => handle_join(shared, input_data, block),
+        Command::Fire => handle_fire(shared, input_data, block),
+        Command::Report => handle_report(shared, input_data, block),
+        Command::Wave => handle_wave(shared, input_data, block),
+        Command::Win => handle_win(shared, input_data, block),
+        Command::Create | Command::Start | Command::Cancel | Command::Claim => Err(
+            ChainResponse::rejected(ErrorCode::WrongAction, "Lobby commands are sent to /lobby"),
+        ),
+        Command::Timeout => Err(ChainResponse::rejected(
+            ErrorCode::WrongAction,
+            "Timeouts are applied by the chain",
+        )),
+    }?;
+    shared.accepted.lock().unwrap().insert(digest);
+    // Quem tem agora a vez tem o prazo todo a partir deste bloco
+    shared
+        .gmap
+        .lock()
+        .unwrap()
+        .get_mut(&gameid)
+        .unwrap()
+        .restart_clock(now);
+    Ok(gameid)
+}
+
+// block and now are the height and timestamp of the block that will record the command
+fn apply_lobby(shared: &SharedData, command: &LobbyCommand, block: u64, now: u64) -> Outcome {
+    shared.events.lock().unwrap().begin_block(block);
+    let outcome = run_lobby(shared, command, now);
+    if let Err(ChainResponse::Rejected { code, message }) = &outcome {
+        shared.events.lock().unwrap().emit_rejected(
+            Some(&command.gameid),
+            EventKind::ReceiptRejected {
+                cmd: command.action.cmd(),
+                fleetid: None,
+                code: *code,
+                message: message.clone(),
+            },
+        );
+    }
+    outcome
+}
+
+fn run_lobby(shared: &SharedData, command: &LobbyCommand, now: u64) -> Outcome {
+    let gameid = &command.gameid;
+    let message = LobbyCommand::signed_message(gameid, &command.action);
     let mut gmap = shared.gmap.lock().unwrap();
 
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(g) => g,
-        None => {
-            let _ = shared
-                .tx
-                .send(format!("Jogo {} não encontrado", data.gameid));
-            return format!("Game {} not found", data.gameid);
+    match &command.action {
+        LobbyAction::Create {
+            rules,
+            min_players,
+            max_players,
+            owner,
+            turns,
+            timeout,
+        } => {
+            if gameid.is_empty() {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::InvalidLobby,
+                    "Game ID cannot be an empty string",
+                ));
+            }
+            if gmap.contains_key(gameid) {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::GameExists,
+                    format!("Game {} already exists", gameid),
+                ));
+            }
+            // A criação é assinada pela chave que fica dona do jogo
+            if !verify_signature(&message, &command.signature, owner) {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::InvalidSignature,
+                    format!("Invalid owner signature for game {}", gameid),
+                ));
+            }
+            rules
+                .validate()
+                .map_err(|e| ChainResponse::rejected(ErrorCode::InvalidLobby, e))?;
+            if *min_players < 2 || max_players < min_players {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::InvalidLobby,
+                    format!(
+                        "Invalid number of players: {} to {}",
+                        min_players, max_players
+                    ),
+                ));
+            }
+            if timeout.is_some_and(|timeout| {
+                timeout.seconds == 0 || timeout.seconds > TurnTimeout::MAX_SECONDS
+            }) {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::InvalidLobby,
+                    format!(
+                        "Turn timeout must be between 1 and {} seconds",
+                        TurnTimeout::MAX_SECONDS
+                    ),
+                ));
+            }
+
+            let seed = shared.rng.lock().unwrap().next_u64();
+            let game = Game::new(
+                rules.clone(),
+                seed,
+                *min_players,
+                *max_players,
+                *owner,
+                *turns,
+                *timeout,
+            );
+            gmap.insert(gameid.clone(), game);
+            emit(
+                shared,
+                gameid,
+                EventKind::GameCreated {
+                    min_players: *min_players,
+                    max_players: *max_players,
+                },
+            );
         }
-    };
+        LobbyAction::Start | LobbyAction::Cancel => {
+            let game = find_game(&mut gmap, gameid)?;
+            // Só quem criou o jogo o pode começar ou cancelar
+            if !verify_signature(&message, &command.signature, &game.owner) {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::InvalidSignature,
+                    format!("Invalid owner signature for game {}", gameid),
+                ));
+            }
+            if command.action == LobbyAction::Start {
+                game.start().map_err(reject)?;
+                game.restart_clock(now);
+                let order = game.order.clone();
+                let seed = game.seed;
+                emit(shared, gameid, EventKind::GameStarted { order, seed });
+                if let Some(next) = game.next_player() {
+                    emit(shared, gameid, EventKind::TurnPassed { next: next.clone() });
+                }
+            } else {
+                game.cancel().map_err(reject)?;
+                emit(shared, gameid, EventKind::GameCancelled);
+            }
+        }
+        LobbyAction::ClaimTimeout { fleetid } => {
+            let game = find_game(&mut gmap, gameid)?;
+            // A vitória é reclamada pela frota, com a chave que registou no join
+            if !game
+                .pubkey(fleetid)
+                .is_some_and(|key| verify_signature(&message, &command.signature, key))
+            {
+                return Err(ChainResponse::rejected(
+                    ErrorCode::InvalidSignature,
+                    format!("Invalid signature for fleet {}", fleetid),
+                ));
+            }
+            let stalled = game.claim_timeout(fleetid, now).map_err(reject)?;
+            game.restart_clock(now);
+            emit_timeout(shared, gameid, game, stalled, TimeoutPolicy::Claim);
+        }
+    }
+    Ok(gameid.clone())
+}
 
-    // Verifica se o jogador alvo está no mesmo jogo procurando pelo nome
-    let player_entry = game.pmap.iter_mut().find(|(_, p)| p.name == data.target);
+// Vigia os prazos das jogadas: de segundo a segundo, os jogos cujo jogador
+// deixou esgotar o prazo são resolvidos pela política do jogo e registados
+async fn watch_deadlines(shared: SharedData) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        expire_turns(&shared);
+    }
+}
 
-    let (target_key, target_player) = match player_entry {
-        Some((key, player)) => (key.clone(), player),
-        None => {
-            let _ = shared.tx.send(format!(
-                "❌ Target player {} not found in game {}",
-                data.target, data.gameid
-            ));
-            return format!("Target player {} is not in this game", data.target);
-        }
-    };
+fn expire_turns(shared: &SharedData) {
+    let mut store = shared.store.lock().unwrap();
+    let (_, now) = next_block_at(shared);
+    let mut expired: Vec<String> = shared
+        .gmap
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, game)| {
+            game.expired(now)
+                && game.timeout.map(|timeout| timeout.policy) != Some(TimeoutPolicy::Claim)
+        })
+        .map(|(gameid, _)| gameid.clone())
+        .collect();
+    // Ordem fixa, a mesma em que o log os vai registar
+    expired.sort();
 
-    //update recorded shots grid
-    //let index =  pos_str_to_index(&data.pos.to_string()).unwrap_or(100); // Convert position to index
-    if let Some(target_player) = game.pmap.get_mut(&data.target) {
-        if data.pos < 100 {
-            target_player.shots[data.pos as usize] = 1; // or 2 if it’s a hit, you decide but it is only on the report that we do this
-            let _ = shared.tx.send(format!(
-                "✅ Shot registered at position ({}) for player {}",
-                data.pos, data.target
-            ));
+    for gameid in expired {
+        let (height, now) = next_block_at(shared);
+        if apply_timeout(shared, &gameid, height, now).is_ok() {
+            let transaction = Transaction::timeout(&gameid);
+            commit(shared, &mut store, transaction, now, |block| {
+                Record::Timeout { block, gameid }
+            });
         }
     }
+}
 
-    //save current shot for report confirmation
-    //game.current_shot = Some(index);
-    game.current_shot = Some((data.pos, data.target.clone()));
-
-    // Lógica simples para demonstrar:
-    // Verifica se é a vez do jogador correto
-    if game.next_player.as_ref() != Some(&data.fleet) {
-        let _ = shared
-            .tx
-            .send(format!("❌ Out-of-order fire by player {}", data.fleetid));
-        return "Not your turn".to_string();
+// block and now are the height and timestamp of the block that records the timeout
+fn apply_timeout(shared: &SharedData, gameid: &str, block: u64, now: u64) -> Outcome {
+    shared.events.lock().unwrap().begin_block(block);
+    let mut gmap = shared.gmap.lock().unwrap();
+    let game = find_game(&mut gmap, gameid)?;
+    let policy = game
+        .timeout
+        .map_or(TimeoutPolicy::Pass, |timeout| timeout.policy);
+    let stalled = game.time_out(now).map_err(reject)?;
+    game.restart_clock(now);
+    emit_timeout(shared, gameid, game, stalled, policy);
+    Ok(gameid.to_string())
+}
+
+// Eventos de uma jogada resolvida por timeout ou por claim: quem a deixou
+// esgotar, se saiu do jogo, e o vencedor ou o próximo a jogar
+fn emit_timeout(
+    shared: &SharedData,
+    gameid: &str,
+    game: &Game,
+    stalled: String,
+    policy: TimeoutPolicy,
+) {
+    emit(
+        shared,
+        gameid,
+        EventKind::TurnTimedOut {
+            fleetid: stalled.clone(),
+            policy,
+        },
+    );
+    if game.pmap[&stalled].forfeited {
+        emit(
+            shared,
+            gameid,
+            EventKind::PlayerForfeited { fleetid: stalled },
+        );
     }
+    emit_turn(shared, gameid, game);
+}
 
-    // Atualizar estado do jogador alvo, ou lógica do jogo ...
-    // Por exemplo, poderia marcar o disparo na board atual do jogador
+// Quem o jogo espera a seguir: o próximo a disparar, ou a última frota à tona,
+// que já só pode reclamar a vitória
+fn emit_turn(shared: &SharedData, gameid: &str, game: &Game) {
+    match &game.phase {
+        Phase::InProgress(Turn::AwaitingFire { player }) => emit(
+            shared,
+            gameid,
+            EventKind::TurnPassed {
+                next: player.clone(),
+            },
+        ),
+        Phase::InProgress(Turn::AwaitingWin { player }) => emit(
+            shared,
+            gameid,
+            EventKind::WinPending {
+                fleetid: player.clone(),
+            },
+        ),
+        _ => {}
+    }
+}
 
-    // Vamos apenas atualizar o current_state do jogador para o novo estado recebido no journal
-    if let Some(player) = game.pmap.get_mut(&data.fleet) {
-        player.current_state = data.board.clone();
+// O jornal diz que ação o guest provou: tem de ser o comando enviado pelo
+// cliente, que é o que escolhe o image ID a verificar
+fn check_header(input_data: &CommunicationData) -> Result<(), ChainResponse> {
+    let header: JournalHeader = input_data.receipt.journal.decode().map_err(|_| {
+        ChainResponse::rejected(ErrorCode::InvalidJournal, "Failed to decode journal header")
+    })?;
+    if header.version != PROTOCOL_VERSION {
+        return Err(ChainResponse::rejected(
+            ErrorCode::UnsupportedVersion,
+            format!(
+                "Unsupported protocol version {}, expected {}",
+                header.version, PROTOCOL_VERSION
+            ),
+        ));
+    }
+    if header.action != input_data.cmd {
+        return Err(ChainResponse::rejected(
+            ErrorCode::WrongAction,
+            format!(
+                "Journal is for a {:?} command, not {:?}",
+                header.action, input_data.cmd
+            ),
+        ));
     }
+    Ok(())
+}
 
-    // Definir o próximo jogador
-    let next_player = game
-        .pmap
-        .keys()
-        .filter(|k| *k != &data.fleet)
-        .choose(&mut *shared.rng.lock().unwrap());
+// Verifica o receipt contra o image ID do comando e descodifica o jornal
+fn open_receipt<T: DeserializeOwned>(
+    input_data: &CommunicationData,
+    image_id: [u32; 8],
+) -> Result<T, ChainResponse> {
+    if input_data.receipt.verify(image_id).is_err() {
+        return Err(ChainResponse::rejected(
+            ErrorCode::InvalidReceipt,
+            "Could not verify receipt",
+        ));
+    }
+    input_data
+        .receipt
+        .journal
+        .decode()
+        .map_err(|_| ChainResponse::rejected(ErrorCode::InvalidJournal, "Failed to decode journal"))
+}
 
-    game.next_player = next_player.cloned();
+fn handle_join(shared: &SharedData, input_data: &CommunicationData, block: u64) -> Outcome {
+    let data: JoinJournal = open_receipt(input_data, JOIN_ID)?;
+    // O join é assinado pela chave que regista, o que prova que o jogador a tem
+    check_signature(input_data, &data.fleetid, Some(&data.pubkey))?;
 
-    let next_player_str = match &game.next_player {
-        Some(fleetid) => fleetid.as_str(),
-        None => "None",
-    };
+    // O jogo tem de ter sido criado no lobby
+    let mut gmap = shared.gmap.lock().unwrap();
+    let game = find_game(&mut gmap, &data.gameid)?;
+    game.join(
+        &data.fleetid,
+        data.seq,
+        data.board,
+        &data.rules,
+        data.pubkey,
+    )
+    .map_err(reject)?;
+    game.history.push(HistoryEntry {
+        block,
+        cmd: Command::Join,
+        fleetid: data.fleetid.clone(),
+        target: None,
+        pos: None,
+        hit: None,
+    });
 
-    // Envia mensagem para broadcast
-    let msg = format!(
-        "🎮 [Game {}] 🔫 Player {} shot on position {} of Player {}",
-        data.gameid,
-        data.fleetid,
-        xy_pos(data.pos),
-        //game.next_player
-        //next_player_str
-        data.target,
+    emit(
+        shared,
+        &data.gameid,
+        EventKind::PlayerJoined {
+            fleetid: data.fleetid.clone(),
+        },
     );
-    let _ = shared.tx.send(msg);
+    Ok(data.gameid)
+}
+
+fn handle_fire(shared: &SharedData, input_data: &CommunicationData, block: u64) -> Outcome {
+    let data: FireJournal = open_receipt(input_data, FIRE_ID)?;
 
-    "OK".to_string()
+    // Trancar o mapa de jogos para alterar o estado
+    let mut gmap = shared.gmap.lock().unwrap();
+    let game = find_game(&mut gmap, &data.gameid)?;
+    check_signature(input_data, &data.fleetid, game.pubkey(&data.fleetid))?;
+
+    game.fire(&data.fleetid, data.seq, data.board, &data.target, data.pos)
+        .map_err(reject)?;
+    game.history.push(HistoryEntry {
+        block,
+        cmd: Command::Fire,
+        fleetid: data.fleetid.clone(),
+        target: Some(data.target.clone()),
+        pos: Some(data.pos),
+        hit: None,
+    });
+
+    // Envia mensagem para broadcast
+    emit(
+        shared,
+        &data.gameid,
+        EventKind::ShotFired {
+            shooter: data.fleetid.clone(),
+            target: data.target.clone(),
+            pos: data.pos,
+        },
+    );
+    Ok(data.gameid)
 }
 
-fn handle_report(shared: &SharedData, input_data: &CommunicationData) -> String {
-    if input_data.receipt.verify(REPORT_ID).is_err() {
-        let _ = shared
-            .tx
-            .send("Tentativa de disparo com receipt inválido".to_string());
-        return "Could not verify receipt".to_string();
-    }
+fn handle_report(shared: &SharedData, input_data: &CommunicationData, block: u64) -> Outcome {
+    let data: ReportJournal = open_receipt(input_data, REPORT_ID)?;
 
-    let data: ReportJournal = match input_data.receipt.journal.decode() {
-        Ok(d) => d,
-        Err(_) => {
-            let _ = shared
-                .tx
-                .send("Erro a decodificar o FireJournal".to_string());
-            return "Failed to decode journal".to_string();
+    let hit = match data.report.as_str() {
+        "Hit" => true,
+        "Miss" => false,
+        other => {
+            return Err(ChainResponse::rejected(
+                ErrorCode::InvalidJournal,
+                format!("Unknown report {}", other),
+            ))
         }
     };
 
     // Trancar o mapa de jogos para alterar o estado
     let mut gmap = shared.gmap.lock().unwrap();
+    let game = find_game(&mut gmap, &data.gameid)?;
+    check_signature(input_data, &data.fleetid, game.pubkey(&data.fleetid))?;
 
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(g) => g,
-        None => {
-            let _ = shared
-                .tx
-                .send(format!("Jogo {} não encontrado", data.gameid));
-            return format!("Game {} not found", data.gameid);
-        }
-    };
+    game.report(
+        &data.fleetid,
+        data.seq,
+        data.board,
+        data.next_board,
+        data.pos,
+        hit,
+    )
+    .map_err(reject)?;
+    game.history.push(HistoryEntry {
+        block,
+        cmd: Command::Report,
+        fleetid: data.fleetid.clone(),
+        target: None,
+        pos: Some(data.pos),
+        hit: Some(hit),
+    });
 
-    // Verificar se o jogador correto está fazendo o report
-    // O report deve ser feito pelo jogador que foi atingido, ou seja, o alvo do tiro
-    if game
-        .current_shot
-        .as_ref()
-        .map_or(false, |(pos, target)| target != &data.fleetid)
-    {
-        let _ = shared.tx.send(format!(
-            "❌ Player {} tried to report, but they are not the target player.",
-            data.fleetid
-        ));
-        return "You are not the target of the shot.".to_string();
+    emit(
+        shared,
+        &data.gameid,
+        EventKind::ShotReported {
+            fleetid: data.fleetid.clone(),
+            pos: data.pos,
+            hit,
+        },
+    );
+    if game.pmap[&data.fleetid].eliminated() {
+        emit(
+            shared,
+            &data.gameid,
+            EventKind::FleetEliminated {
+                fleetid: data.fleetid.clone(),
+            },
+        );
     }
+    emit_turn(shared, &data.gameid, game);
+    Ok(data.gameid)
+}
 
-    // Se o jogador correto está fazendo o report, verifique se a posição é válida
-    if let Some((expected_index, expected_target)) = &game.current_shot {
-        if *expected_index == data.pos && expected_target == &data.fleetid {
-            // Processar o report
-            let action = match data.report {
-                0 => {
-                    // Hit
-                    if let Some(target_player) = game.pmap.get_mut(&data.fleetid) {
-                        if target_player.shots[data.pos as usize] == 2 {
-                            let _ = shared.tx.send(format!(
-                                "❌ Shot already hit at position ({}) for player {}",
-                                data.pos, data.fleetid
-                            ));
-                            return "Shot already hit".to_string();
-                        }
-
-                        target_player.hit_count += 1;
-                        if data.pos < 100 {
-                            target_player.shots[data.pos as usize] = 2; // Marca o tiro como acerto (2)
-                            let _ = shared.tx.send(format!(
-                                "✅ Shot registered at position ({}) for player {}",
-                                data.pos, data.fleetid
-                            ));
-                        }
-                    }
-                    "💥 Hit confirmed"
-                }
-                1 => "💨 Missed shot", // Miss
-                _ => "Unknown report", // fallback case
-            };
+fn handle_wave(shared: &SharedData, input_data: &CommunicationData, block: u64) -> Outcome {
+    let data: BaseJournal = open_receipt(input_data, WAVE_ID)?;
 
-            let msg = format!(
-                "🎮 [Game {}] Player {} {} at {}.",
-                data.gameid,
-                data.fleetid,
-                action,
-                xy_pos(data.pos),
-            );
-            let _ = shared.tx.send(msg);
-
-            // Atualizar o próximo jogador
-            let next_player = game
-                .pmap
-                .keys()
-                .filter(|k| *k != &data.fleetid)
-                .choose(&mut *shared.rng.lock().unwrap());
-
-            game.next_player = next_player.cloned();
-        } else {
-            let _ = shared.tx.send(format!(
-                "🎮 [Game {}] ⚠️ Report mismatch: expected report on shot at position {} on player {}, but got position {} on player {}. Report it correctly please.",
-                data.gameid,
-                xy_pos(*expected_index),
-                expected_target,
-                xy_pos(data.pos),
-                data.fleetid
-            ));
-        }
+    let mut gmap = shared.gmap.lock().unwrap();
+    let game = find_game(&mut gmap, &data.gameid)?;
+    check_signature(input_data, &data.fleetid, game.pubkey(&data.fleetid))?;
+
+    game.wave(&data.fleetid, data.seq, data.board)
+        .map_err(reject)?;
+    game.history.push(HistoryEntry {
+        block,
+        cmd: Command::Wave,
+        fleetid: data.fleetid.clone(),
+        target: None,
+        pos: None,
+        hit: None,
+    });
+
+    if let Some(next) = game.next_player() {
+        emit(
+            shared,
+            &data.gameid,
+            EventKind::TurnPassed { next: next.clone() },
+        );
     }
-    "OK".to_string()
+    Ok(data.gameid)
 }
 
-fn handle_wave(shared: &SharedData, input_data: &CommunicationData) -> String {
-    if input_data.receipt.verify(WAVE_ID).is_err() {
-        let _ = shared
-            .tx
-            .send("Tentativa de wave com receipt inválido".to_string());
-        return "Could not verify receipt".to_string();
-    }
-    let data: BaseJournal = match input_data.receipt.journal.decode() {
-        Ok(d) => d,
-        Err(_) => {
-            let _ = shared
-                .tx
-                .send("Erro a decodificar o BaseJournal (wave)".to_string());
-            return "Failed to decode journal".to_string();
-        }
-    };
+fn handle_win(shared: &SharedData, input_data: &CommunicationData, block: u64) -> Outcome {
+    let data: BaseJournal = open_receipt(input_data, WIN_ID)?;
 
     let mut gmap = shared.gmap.lock().unwrap();
-    let game = match gmap.get_mut(&data.gameid) {
-        Some(g) => g,
-        None => return format!("Game {} not found", data.gameid),
-    };
+    let game = find_game(&mut gmap, &data.gameid)?;
+    check_signature(input_data, &data.fleetid, game.pubkey(&data.fleetid))?;
 
-    // Precaução: só pode fazer wave se for a sua vez
-    if game.next_player.as_ref() != Some(&data.fleetid) {
-        let _ = shared
-            .tx
-            .send(format!("❌ Out-of-order wave by player {}", data.fleetid));
-        return "Not your turn".to_string();
-    }
+    game.win(&data.fleetid, data.seq, data.board)
+        .map_err(reject)?;
+    game.history.push(HistoryEntry {
+        block,
+        cmd: Command::Win,
+        fleetid: data.fleetid.clone(),
+        target: None,
+        pos: None,
+        hit: None,
+    });
 
-    // Atualiza o próximo jogador
-    let next_player = game
-        .pmap
-        .keys()
-        .filter(|k| *k != &data.fleetid)
-        .choose(&mut *shared.rng.lock().unwrap());
-    game.next_player = next_player.cloned();
-
-    let msg = format!(
-        "🎮 [Game {}] 👋 Player {} waved the turn.",
-        data.gameid, data.fleetid
+    emit(
+        shared,
+        &data.gameid,
+        EventKind::GameWon {
+            winner: data.fleetid.clone(),
+        },
     );
-    let _ = shared.tx.send(msg);
-    "OK".to_string()
+    Ok(data.gameid)
+}
+
+fn find_game<'a>(
+    gmap: &'a mut HashMap<String, Game>,
+    gameid: &str,
+) -> Result<&'a mut Game, ChainResponse> {
+    gmap.get_mut(gameid).ok_or_else(|| {
+        ChainResponse::rejected(
+            ErrorCode::GameNotFound,
+            format!("Game {} not found", gameid),
+        )
+    })
+}
+
+// O comando tem de vir assinado pela chave registada pela frota no join
+fn check_signature(
+    input_data: &CommunicationData,
+    fleetid: &str,
+    pubkey: Option<&[u8; 32]>,
+) -> Result<(), ChainResponse> {
+    let message = CommunicationData::signed_message(input_data.cmd, &input_data.receipt);
+    let valid = pubkey.is_some_and(|key| verify_signature(&message, &input_data.signature, key));
+    if !valid {
+        return Err(ChainResponse::rejected(
+            ErrorCode::InvalidSignature,
+            format!("Invalid signature for fleet {}", fleetid),
+        ));
+    }
+    Ok(())
+}
+
+fn verify_signature(message: &[u8], signature: &[u8], pubkey: &[u8; 32]) -> bool {
+    match (
+        VerifyingKey::from_bytes(pubkey),
+        Signature::from_slice(signature),
+    ) {
+        (Ok(key), Ok(signature)) => key.verify(message, &signature).is_ok(),
+        _ => false,
+    }
+}
+
+// Comando rejeitado pela máquina de estados do jogo, que fica intacto
+fn reject(e: GameError) -> ChainResponse {
+    ChainResponse::rejected(e.code(), e.to_string())
+}
+
+fn emit(shared: &SharedData, gameid: &str, kind: EventKind) {
+    shared.events.lock().unwrap().emit(Some(gameid), kind);
 }
 
-fn handle_win(shared: &SharedData, input_data: &CommunicationData) -> String {
-    // TO DO:
-    "OK".to_string()
+// Start of every journal, enough to say who a rejected command claimed to be from
+#[derive(Deserialize)]
+struct JournalOrigin {
+    header: JournalHeader,
+    fleetid: String,
+    gameid: String,
 }
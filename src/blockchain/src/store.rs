@@ -0,0 +1,101 @@
+// Append-only log of everything the chain accepted, one JSON record per line.
+// The game state is never written directly: replaying the records in order
+// through the same handlers rebuilds it after a restart or a crash.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+use fleetcore::{CommunicationData, LobbyCommand};
+
+use crate::ledger::Block;
+
+#[derive(Deserialize, Serialize)]
+pub enum Record {
+    // First record of a log: seed of the rng that seeds every new game, so
+    // that a replay makes exactly the same choices
+    Genesis {
+        seed: u64,
+    },
+    // A command whose receipt was verified and accepted, with the block that
+    // recorded it in the ledger
+    Accepted {
+        block: Block,
+        data: CommunicationData,
+    },
+    // A lobby operation signed by the game's owner and accepted, with its block
+    Lobby {
+        block: Block,
+        command: LobbyCommand,
+    },
+    // A turn the chain found expired and settled by the game's timeout policy
+    Timeout {
+        block: Block,
+        gameid: String,
+    },
+}
+
+pub struct Store {
+    file: File,
+}
+
+impl Store {
+    // Opens (or creates) the log and returns the records already in it.
+    // A torn last line, left by a crash in the middle of a write, is dropped;
+    // any other unreadable record fails the open and leaves the file untouched.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<(Store, Vec<Record>)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let (records, valid_len) = read_records(&mut file)?;
+        file.set_len(valid_len)?;
+
+        Ok((Store { file }, records))
+    }
+
+    // Reads the records of an existing log without touching the file, for audits
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<Record>> {
+        let mut file = File::open(path)?;
+        Ok(read_records(&mut file)?.0)
+    }
+
+    // Writes the record and waits for it to reach the disk
+    pub fn append(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()
+    }
+}
+
+// Reads every record of the log, returning them with the length in bytes of
+// the complete lines. Only a last line without its newline, torn by a crash in
+// the middle of a write, is left out; any other unreadable line is an error, so
+// that a log the chain no longer understands is never cut short.
+fn read_records(file: &mut File) -> io::Result<(Vec<Record>, u64)> {
+    let mut records = Vec::new();
+    let mut valid_len = 0u64;
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if !line.ends_with('\n') {
+            eprintln!("Discarding torn record at byte {} of the log", valid_len);
+            break;
+        }
+        let record = serde_json::from_str::<Record>(line.trim_end()).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unreadable record at byte {} of the log: {}", valid_len, e),
+            )
+        })?;
+        records.push(record);
+        valid_len += line.len() as u64;
+        line.clear();
+    }
+    Ok((records, valid_len))
+}
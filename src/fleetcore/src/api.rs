@@ -0,0 +1,226 @@
+// Answers of the blockchain to its clients, serialized as JSON. A rejected command
+// carries a stable error code that clients can match on, the message is for people.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Command, Coord, GameRules, TimeoutPolicy, TurnPolicy, TurnTimeout};
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Turn {
+    // The player may fire or wave
+    AwaitingFire {
+        player: String,
+    },
+    // A shot was fired and only its target may report it. Every fire in the
+    // game waits for this report; the turn moves on only once it is accepted
+    AwaitingReport {
+        shooter: String,
+        target: String,
+        pos: Coord,
+    },
+    // Every other fleet is out; the game ends once the last one proves its win
+    AwaitingWin {
+        player: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Phase {
+    // Players are joining until the owner starts the game
+    Lobby,
+    // Started with the roster locked; the first one to join fires first
+    InProgress(Turn),
+    Finished { winner: String },
+    // Cancelled by the owner before it started
+    Cancelled,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PlayerState {
+    pub fleetid: String,
+    pub forfeited: bool,  // out of the game for letting a turn expire
+    pub eliminated: bool, // out of the game with every ship sunk
+    pub hits: u32,        // hits taken, the fleet is sunk at rules.fleet_cells()
+    pub seq: u32,         // sequence number of the player's last accepted command
+    pub shots: Vec<u8>,   // shots taken per cell: 0 = none, 1 = fired (miss), 2 = hit
+}
+
+// Public state of a game, as seen by the chain
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GameState {
+    pub gameid: String,
+    pub rules: GameRules,
+    pub seed: u64, // seed of the game's rng, drives the SeededRandom turn policy
+    pub min_players: u32,
+    pub max_players: u32,
+    pub turns: TurnPolicy,
+    pub timeout: Option<TurnTimeout>,
+    pub deadline: Option<u64>, // when the current turn expires, in seconds since the UNIX epoch
+    pub phase: Phase,
+    pub players: Vec<PlayerState>, // in join order, the RoundRobin turn order
+}
+
+// A command accepted in a game, with its public data
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub block: u64, // height of the block that recorded it
+    pub cmd: Command,
+    pub fleetid: String,
+    pub target: Option<String>, // fire
+    pub pos: Option<Coord>,     // fire and report
+    pub hit: Option<bool>,      // report
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // The packet itself
+    InvalidReceipt,
+    InvalidJournal,
+    UnsupportedVersion,
+    WrongAction,
+    InvalidSignature,
+    DuplicateReceipt,
+    OutOfSequence,
+    GameNotFound,
+    // The lobby
+    GameExists,
+    InvalidLobby,
+    GameFull,
+    GameCancelled,
+    // Turn deadlines
+    NotTimedOut,
+    ClaimNotAllowed,
+    // The game rules
+    GameStarted,
+    GameFinished,
+    GameNotStarted,
+    DifferentRules,
+    AlreadyJoined,
+    NotEnoughPlayers,
+    UnknownPlayer,
+    UnknownTarget,
+    NotYourTurn,
+    SelfTarget,
+    AlreadyFired,
+    FleetOut,
+    ReportPending,
+    WinPending,
+    NoShotPending,
+    NotTarget,
+    ReportMismatch,
+    OutsideBoard,
+    BoardMismatch,
+    FleetsAfloat,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ChainResponse {
+    // The command was recorded in the given block, leaving the game in this
+    // state. Boxed so a rejection stays small; it serializes the same
+    Accepted { block: u64, game: Box<GameState> },
+    Rejected { code: ErrorCode, message: String },
+}
+
+impl ChainResponse {
+    pub fn rejected(code: ErrorCode, message: impl Into<String>) -> Self {
+        ChainResponse::Rejected {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// Result of re-validating the ledger from genesis, answered by /blocks/verify
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    pub height: u64,           // height of the last block of the ledger
+    pub error: Option<String>, // first broken link, when not valid
+}
+
+// Something that happened on the chain, streamed to the clients of /logs
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EventKind {
+    GameCreated {
+        min_players: u32,
+        max_players: u32,
+    },
+    // The turn order, fixed from now on, and the seed of the SeededRandom draws
+    GameStarted {
+        order: Vec<String>,
+        seed: u64,
+    },
+    GameCancelled,
+    PlayerJoined {
+        fleetid: String,
+    },
+    ShotFired {
+        shooter: String,
+        target: String,
+        pos: Coord,
+    },
+    ShotReported {
+        fleetid: String,
+        pos: Coord,
+        hit: bool,
+    },
+    TurnPassed {
+        next: String,
+    },
+    GameWon {
+        winner: String,
+    },
+    // fleetid let the deadline expire, policy says what happened next
+    TurnTimedOut {
+        fleetid: String,
+        policy: TimeoutPolicy,
+    },
+    PlayerForfeited {
+        fleetid: String,
+    },
+    // Every ship of the fleet was hit; it is skipped from now on
+    FleetEliminated {
+        fleetid: String,
+    },
+    // The last fleet afloat, which may now claim the win
+    WinPending {
+        fleetid: String,
+    },
+    // fleetid is what the journal claims, the receipt may not even verify
+    ReceiptRejected {
+        cmd: Command,
+        fleetid: Option<String>,
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChainEvent {
+    pub id: u64, // increasing, a reconnecting client resumes after the last id it saw
+    pub gameid: Option<String>,
+    pub kind: EventKind,
+}
+
+impl ChainEvent {
+    // Whether the event is about the given fleet
+    pub fn involves(&self, fleetid: &str) -> bool {
+        match &self.kind {
+            EventKind::GameCreated { .. } | EventKind::GameCancelled => false,
+            EventKind::GameStarted { order, .. } => order.iter().any(|f| f == fleetid),
+            EventKind::PlayerJoined { fleetid: f }
+            | EventKind::ShotReported { fleetid: f, .. }
+            | EventKind::TurnTimedOut { fleetid: f, .. }
+            | EventKind::PlayerForfeited { fleetid: f }
+            | EventKind::FleetEliminated { fleetid: f }
+            | EventKind::WinPending { fleetid: f } => f == fleetid,
+            EventKind::ShotFired {
+                shooter, target, ..
+            } => shooter == fleetid || target == fleetid,
+            EventKind::TurnPassed { next } => next == fleetid,
+            EventKind::GameWon { winner } => winner == fleetid,
+            EventKind::ReceiptRejected { fleetid: f, .. } => f.as_deref() == Some(fleetid),
+        }
+    }
+}
@@ -0,0 +1,369 @@
+// Typed board model shared by the guests, the host and the blockchain.
+// Ships and cells are kept in ordered vectors (no hash based collections) so
+// validation and commitments come out the same in the guests and on the host.
+
+use core::fmt;
+use core::str::FromStr;
+
+use risc0_zkvm::Digest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::GameRules;
+
+// A cell of the board: x is the column (shown as a letter), y the row
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+pub struct Coord {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Coord {
+    pub fn new(x: u8, y: u8) -> Self {
+        Coord { x, y }
+    }
+
+    // Linear index used by the web page and the shot grids (row major)
+    pub fn from_index(index: usize, width: u8) -> Self {
+        let width = width as usize;
+        Coord {
+            x: (index % width) as u8,
+            y: (index / width) as u8,
+        }
+    }
+
+    pub fn index(&self, width: u8) -> usize {
+        self.y as usize * width as usize + self.x as usize
+    }
+
+    // Shares a side with other
+    pub fn is_adjacent(&self, other: &Coord) -> bool {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y) == 1
+    }
+
+    // Shares a side or a corner with other
+    pub fn touches(&self, other: &Coord) -> bool {
+        self != other && self.x.abs_diff(other.x) <= 1 && self.y.abs_diff(other.y) <= 1
+    }
+}
+
+// Displayed as column letter and row number, e.g. A0 or J9
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", b'A'.wrapping_add(self.x) as char, self.y)
+    }
+}
+
+impl FromStr for Coord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let col = s
+            .chars()
+            .next()
+            .ok_or_else(|| "Empty coordinate".to_string())?
+            .to_ascii_uppercase();
+        if !col.is_ascii_uppercase() {
+            return Err(format!("Invalid column in coordinate {}", s));
+        }
+        let y: u8 = s[1..]
+            .parse()
+            .map_err(|_| format!("Invalid row in coordinate {}", s))?;
+        Ok(Coord::new(col as u8 - b'A', y))
+    }
+}
+
+// A straight line of orthogonally adjacent cells
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Ship {
+    pub cells: Vec<Coord>,
+}
+
+impl Ship {
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn is_straight(&self) -> bool {
+        match self.cells.first() {
+            Some(first) => {
+                self.cells.iter().all(|c| c.y == first.y)
+                    || self.cells.iter().all(|c| c.x == first.x)
+            }
+            None => false,
+        }
+    }
+
+    pub fn touches(&self, other: &Ship) -> bool {
+        self.cells
+            .iter()
+            .any(|a| other.cells.iter().any(|b| a.touches(b)))
+    }
+}
+
+// Displayed by its end cells, e.g. A0-E0
+impl fmt::Display for Ship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.cells.first(), self.cells.last()) {
+            (Some(first), Some(last)) if first != last => write!(f, "{}-{}", first, last),
+            (Some(first), _) => write!(f, "{}", first),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The ships found on a board
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Fleet {
+    pub ships: Vec<Ship>,
+}
+
+impl Fleet {
+    // Checks the ship sizes against the rules and, with no_touching, that no two
+    // ships touch diagonally (orthogonal contact would have merged them already)
+    pub fn validate(&self, rules: &GameRules) -> Result<(), String> {
+        let mut found: Vec<usize> = self.ships.iter().map(Ship::len).collect();
+        found.sort_unstable();
+        let mut expected = rules.ship_sizes.clone();
+        expected.sort_unstable();
+        if found != expected {
+            return Err(format!(
+                "Frota inválida: tamanhos encontrados {:?}, esperados {:?}",
+                found, expected
+            ));
+        }
+
+        if rules.no_touching {
+            for (i, a) in self.ships.iter().enumerate() {
+                if let Some(b) = self.ships[i + 1..].iter().find(|b| a.touches(b)) {
+                    return Err(format!("Navios encostados: {} e {}", a, b));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Fleet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, ship) in self.ships.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}", ship)?;
+        }
+        Ok(())
+    }
+}
+
+// The private state of a player: the ship cells still afloat
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Board {
+    pub cells: Vec<Coord>,
+}
+
+impl Board {
+    pub fn new(cells: Vec<Coord>) -> Self {
+        Board { cells }
+    }
+
+    pub fn from_indices(indices: &[usize], width: u8) -> Self {
+        Board::new(
+            indices
+                .iter()
+                .map(|&i| Coord::from_index(i, width))
+                .collect(),
+        )
+    }
+
+    pub fn indices(&self, width: u8) -> Vec<usize> {
+        self.cells.iter().map(|c| c.index(width)).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, pos: &Coord) -> bool {
+        self.cells.contains(pos)
+    }
+
+    // The board left after a shot at pos, which sinks that cell if it was afloat
+    pub fn without(&self, pos: &Coord) -> Board {
+        Board::new(self.cells.iter().copied().filter(|c| c != pos).collect())
+    }
+
+    // Hiding commitment to the board: SHA-256 over the length-prefixed cell
+    // coordinates followed by the random nonce
+    pub fn commit(&self, random: &str) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update((self.cells.len() as u32).to_le_bytes());
+        for cell in &self.cells {
+            hasher.update([cell.x, cell.y]);
+        }
+        hasher.update(random.as_bytes());
+        Digest::try_from(hasher.finalize().as_slice()).unwrap()
+    }
+
+    // Groups the cells into ships, rejecting cells outside the board, overlapping
+    // cells and ships that are not straight lines
+    pub fn fleet(&self, rules: &GameRules) -> Result<Fleet, String> {
+        for (i, cell) in self.cells.iter().enumerate() {
+            if !rules.contains(cell) {
+                return Err(format!("Navio fora do tabuleiro: {:?}", cell));
+            }
+            if self.cells[..i].contains(cell) {
+                return Err(format!("Sobreposição de navios na posição: {}", cell));
+            }
+        }
+
+        // Agrupa posições adjacentes em barcos (horizontal ou vertical)
+        let mut restantes = self.cells.clone();
+        let mut ships = Vec::new();
+        while let Some(inicio) = restantes.pop() {
+            let mut ship = vec![inicio];
+            let mut i = 0;
+            while i < ship.len() {
+                let cell = ship[i];
+                let (vizinhos, resto): (Vec<Coord>, Vec<Coord>) =
+                    restantes.into_iter().partition(|c| c.is_adjacent(&cell));
+                ship.extend(vizinhos);
+                restantes = resto;
+                i += 1;
+            }
+            ship.sort_unstable();
+            let ship = Ship { cells: ship };
+            if !ship.is_straight() {
+                return Err(format!("Navio não está em linha reta: {}", inicio));
+            }
+            ships.push(ship);
+        }
+        Ok(Fleet { ships })
+    }
+
+    // A legal fleet according to the game rules
+    pub fn validate(&self, rules: &GameRules) -> Result<Fleet, String> {
+        let fleet = self.fleet(rules)?;
+        fleet.validate(rules)?;
+        Ok(fleet)
+    }
+}
+
+// Displayed as the comma separated list of its cells, e.g. A0,B0,C0
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, cell) in self.cells.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", cell)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|c| !c.trim().is_empty())
+            .map(Coord::from_str)
+            .collect::<Result<Vec<Coord>, String>>()
+            .map(Board::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 5x5 board with one ship of each size 3, 2 and 1
+    fn rules(no_touching: bool) -> GameRules {
+        GameRules {
+            width: 5,
+            height: 5,
+            ship_sizes: vec![3, 2, 1],
+            no_touching,
+        }
+    }
+
+    fn board(cells: &str) -> Board {
+        cells.parse().unwrap()
+    }
+
+    fn rejects(cells: &str, rules: &GameRules, reason: &str) {
+        let err = board(cells).validate(rules).unwrap_err();
+        assert!(err.contains(reason), "{}: {}", cells, err);
+    }
+
+    #[test]
+    fn accepts_legal_fleet() {
+        let fleet = board("A0,B0,C0,A2,A3,E4").validate(&rules(true)).unwrap();
+        let mut sizes: Vec<usize> = fleet.ships.iter().map(Ship::len).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_cell_outside_board() {
+        rejects("A0,B0,C0,A2,A3,F4", &rules(false), "fora do tabuleiro");
+        rejects("A0,B0,C0,A2,A3,E5", &rules(false), "fora do tabuleiro");
+    }
+
+    #[test]
+    fn rejects_overlapping_cells() {
+        rejects("A0,B0,C0,A2,A3,B0", &rules(false), "Sobreposição");
+    }
+
+    #[test]
+    fn rejects_bent_ship() {
+        rejects("A0,B0,B1,A3,A4,E4", &rules(false), "linha reta");
+    }
+
+    #[test]
+    fn rejects_wrong_ship_sizes() {
+        // Same number of cells, but sizes 2, 2, 1, 1 instead of 3, 2, 1
+        rejects("A0,B0,A2,B2,E0,E4", &rules(false), "tamanhos");
+        // Ships that touch orthogonally merge into a longer one
+        rejects("A0,B0,C0,D0,E0,A4", &rules(false), "tamanhos");
+    }
+
+    #[test]
+    fn no_touching_rejects_diagonal_contact() {
+        let cells = "A0,B0,C0,D1,D2,A4";
+        assert!(board(cells).validate(&rules(false)).is_ok());
+        rejects(cells, &rules(true), "encostados");
+    }
+
+    #[test]
+    fn coord_display_round_trips() {
+        for x in 0..26 {
+            for y in 0..26 {
+                let coord = Coord::new(x, y);
+                assert_eq!(coord.to_string().parse::<Coord>(), Ok(coord));
+            }
+        }
+        assert_eq!(Coord::new(25, 25).to_string(), "Z25");
+    }
+
+    #[test]
+    fn coord_parses_lowercase_and_spaces() {
+        assert_eq!(" c7 ".parse::<Coord>(), Ok(Coord::new(2, 7)));
+    }
+
+    #[test]
+    fn coord_rejects_malformed_input() {
+        for input in ["", "A", "A-1", "A256", "7A", "AB"] {
+            assert!(input.parse::<Coord>().is_err(), "{}", input);
+        }
+    }
+}
@@ -1,5 +1,19 @@
 use risc0_zkvm::{Digest, Receipt};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+mod api;
+mod board;
+mod lobby;
+mod rules;
+
+pub use api::{
+    ChainEvent, ChainResponse, ChainVerification, ErrorCode, EventKind, GameState, HistoryEntry,
+    Phase, PlayerState, Turn,
+};
+pub use board::{Board, Coord, Fleet, Ship};
+pub use lobby::{LobbyAction, LobbyCommand, TimeoutPolicy, TurnPolicy, TurnTimeout};
+pub use rules::GameRules;
 
 // Struct sent by the rust code for input on the methods join, wave and win
 // The struct is read by the zkvm code and the data is used to generate the output Journal
@@ -7,9 +21,11 @@ use serde::{Deserialize, Serialize};
 pub struct BaseInputs {
     pub fleetid: String,
     pub gameid: String,
-    pub fleet: String,
-    pub board: Vec<u8>,
+    pub board: Board,
     pub random: String,
+    pub rules: GameRules, // only checked by join, the chain holds the game to them afterwards
+    pub seq: u32,         // position of the command among the player's commands, join is 0
+    pub pubkey: [u8; 32], // Ed25519 public key of the player, registered by join
 }
 
 // Struct sent by the rust code for input on the methods fire and report
@@ -18,21 +34,30 @@ pub struct BaseInputs {
 pub struct FireInputs {
     pub fleetid: String,
     pub gameid: String,
-    pub fleet: String,
-    pub board: Vec<u8>,
+    pub board: Board,
     pub random: String,
     pub target: String,
-    pub pos: u8,
+    pub pos: Coord,
+    pub seq: u32,
 }
 
 // Enum used to define the command that will be sent to the server by the host in the communication packet
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Command {
+    #[default]
     Join,
     Fire,
     Report,
     Wave,
     Win,
+    // Lobby operations, sent as a LobbyCommand instead of a receipt
+    Create,
+    Start,
+    Cancel,
+    // Turn deadlines: a win claimed over an expired turn, and a timeout
+    // applied by the chain itself
+    Claim,
+    Timeout,
 }
 
 // Struct used to specify the packet sent from the client to the blockchain server
@@ -40,36 +65,88 @@ pub enum Command {
 pub struct CommunicationData {
     pub cmd: Command,
     pub receipt: Receipt,
+    pub signature: Vec<u8>, // Ed25519 signature of signed_message by the fleet's key
+}
+
+impl CommunicationData {
+    // Bytes signed by the player: the command and the digest of the journal, so a
+    // signature cannot be reused with another receipt or another command
+    pub fn signed_message(cmd: Command, receipt: &Receipt) -> Vec<u8> {
+        let mut message = vec![cmd as u8];
+        message.extend_from_slice(&Sha256::digest(&receipt.journal.bytes));
+        message
+    }
+}
+
+// Journals are public: they only carry the game id, the public fleet id and
+// board commitments. The fleet placement itself never leaves the guest.
+// Every journal also carries the player's sequence number: the chain accepts
+// each number once and in order, so an old receipt cannot be submitted again.
+
+// Version of the journal layout, bumped whenever a journal changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// First field of every journal: the action the guest proved and the protocol
+// version. The chain decodes it on its own and checks it against the command
+// before decoding the rest of the journal.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct JournalHeader {
+    pub action: Command,
+    pub version: u32,
 }
 
-// Struct to specify the  output journal for join, wave and win methods
+impl JournalHeader {
+    pub fn new(action: Command) -> Self {
+        JournalHeader {
+            action,
+            version: PROTOCOL_VERSION,
+        }
+    }
+}
+
+// Struct to specify the  output journal for join method
+#[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
+pub struct JoinJournal {
+    pub header: JournalHeader,
+    pub fleetid: String,
+    pub gameid: String,
+    pub board: Digest,
+    pub rules: GameRules,
+    pub seq: u32,
+    pub pubkey: [u8; 32], // key that must sign every later command of the fleet
+}
+
+// Struct to specify the  output journal for wave and win methods
 #[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
 pub struct BaseJournal {
+    pub header: JournalHeader,
     pub fleetid: String,
     pub gameid: String,
-    pub fleet: String,
     pub board: Digest,
+    pub seq: u32,
 }
 
 // Struct to specify the  output journal for fire method
 #[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
 pub struct FireJournal {
+    pub header: JournalHeader,
     pub fleetid: String,
     pub gameid: String,
-    pub fleet: String,
     pub board: Digest,
     pub target: String,
-    pub pos: u8,
-    pub check: u8, // 1 if hit, 0 if miss
+    pub pos: Coord,
+    pub seq: u32,
 }
 
 // Struct to specify the  output journal for report method
 #[derive(Deserialize, PartialEq, Eq, Serialize, Default)]
 pub struct ReportJournal {
+    pub header: JournalHeader,
+    pub fleetid: String,
     pub gameid: String,
-    pub fleet: String,
     pub report: String,
-    pub pos: u8,
+    pub pos: Coord,
     pub board: Digest,
     pub next_board: Digest,
+    pub seq: u32,
 }
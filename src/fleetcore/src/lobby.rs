@@ -0,0 +1,136 @@
+// Lobby operations: creating a game, starting it once enough players joined and
+// cancelling it before it starts, plus claiming an expired turn. They
+// involve no board, so they carry no proof; they are signed instead, by the key
+// given when creating the game (or by the claiming fleet's key for a claim).
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{Command, GameRules};
+
+// How the chain hands the turn on once a shot is reported or a player waves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TurnPolicy {
+    // The next player in join order
+    RoundRobin,
+    // Any other player, drawn from an rng whose seed the chain publishes with
+    // the game, so anyone can check the draws
+    SeededRandom,
+    // The shooter plays again after a hit, otherwise as RoundRobin
+    ShooterAgainOnHit,
+}
+
+impl TurnPolicy {
+    // Policy chosen by name in the host page
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "round-robin" => Some(TurnPolicy::RoundRobin),
+            "seeded-random" => Some(TurnPolicy::SeededRandom),
+            "shooter-again" => Some(TurnPolicy::ShooterAgainOnHit),
+            _ => None,
+        }
+    }
+}
+
+// What happens to a player who lets the turn deadline expire
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TimeoutPolicy {
+    // The chain passes the turn on. A shot cannot be waited out: a player who
+    // does not report it is taken out as with Forfeit
+    Pass,
+    // The chain takes the player out of the game
+    Forfeit,
+    // Nothing happens until another player claims the turn; the claim then
+    // takes the player out as Forfeit does
+    Claim,
+}
+
+impl TimeoutPolicy {
+    // Policy chosen by name in the host page
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "pass" => Some(TimeoutPolicy::Pass),
+            "forfeit" => Some(TimeoutPolicy::Forfeit),
+            "claim" => Some(TimeoutPolicy::Claim),
+            _ => None,
+        }
+    }
+}
+
+// Time a player has to fire or to report a shot, counted from the block that
+// gave them the turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TurnTimeout {
+    pub seconds: u64,
+    pub policy: TimeoutPolicy,
+}
+
+impl TurnTimeout {
+    // Longest turn a game may be created with: one week
+    pub const MAX_SECONDS: u64 = 7 * 24 * 60 * 60;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LobbyAction {
+    // Opens the game for joins. owner is the Ed25519 key that must sign the
+    // later Start or Cancel of the game
+    Create {
+        rules: GameRules,
+        min_players: u32,
+        max_players: u32,
+        owner: [u8; 32],
+        turns: TurnPolicy,
+        timeout: Option<TurnTimeout>, // None: turns never expire
+    },
+    // Locks the roster: nobody joins afterwards and the turn order is fixed
+    Start,
+    // Closes a game that has not started
+    Cancel,
+    // Takes out the player who let the deadline expire in a game whose
+    // TimeoutPolicy is Claim; the game is won once one fleet is left. Signed by
+    // the claiming fleet's key, not the owner's
+    ClaimTimeout {
+        fleetid: String,
+    },
+}
+
+impl LobbyAction {
+    pub fn cmd(&self) -> Command {
+        match self {
+            LobbyAction::Create { .. } => Command::Create,
+            LobbyAction::Start => Command::Start,
+            LobbyAction::Cancel => Command::Cancel,
+            LobbyAction::ClaimTimeout { .. } => Command::Claim,
+        }
+    }
+}
+
+// Packet sent by the host to the /lobby endpoint of the blockchain
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LobbyCommand {
+    pub gameid: String,
+    pub action: LobbyAction,
+    pub signature: Vec<u8>, // Ed25519 signature of signed_message by the owner's key
+}
+
+impl LobbyCommand {
+    // Bytes signed by the owner: the command and a digest of the game id and
+    // the action, so a signature only ever applies to one operation on one game
+    pub fn signed_message(gameid: &str, action: &LobbyAction) -> Vec<u8> {
+        let mut message = vec![action.cmd() as u8];
+        message.extend_from_slice(&Self::digest_of(gameid, action));
+        message
+    }
+
+    // SHA-256 of the game id and the action, recorded in the ledger like the
+    // journal digest of a receipt
+    pub fn digest(&self) -> [u8; 32] {
+        Self::digest_of(&self.gameid, &self.action)
+    }
+
+    fn digest_of(gameid: &str, action: &LobbyAction) -> [u8; 32] {
+        let words = risc0_zkvm::serde::to_vec(&(gameid, action)).unwrap();
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        Sha256::digest(&bytes).into()
+    }
+}
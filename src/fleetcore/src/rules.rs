@@ -0,0 +1,97 @@
+// Rules of a game, chosen by its creator in LobbyAction::Create. Every join
+// journal commits to them and the blockchain rejects a join proved under other
+// rules, so every player of a game is held to the same board size and fleet
+// composition.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Coord;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GameRules {
+    pub width: u8,
+    pub height: u8,
+    pub ship_sizes: Vec<usize>,
+    pub no_touching: bool, // ships may not touch each other, not even diagonally
+}
+
+impl GameRules {
+    // Classic 10x10 game
+    pub fn standard() -> Self {
+        GameRules {
+            width: 10,
+            height: 10,
+            ship_sizes: vec![5, 4, 3, 2, 2, 1, 1],
+            no_touching: false,
+        }
+    }
+
+    // Smaller 8x8 board for quick games
+    pub fn quick() -> Self {
+        GameRules {
+            width: 8,
+            height: 8,
+            ship_sizes: vec![4, 3, 2, 2, 1],
+            no_touching: false,
+        }
+    }
+
+    // Larger 12x12 board used in tournaments
+    pub fn tournament() -> Self {
+        GameRules {
+            width: 12,
+            height: 12,
+            ship_sizes: vec![5, 4, 4, 3, 3, 2, 2, 1, 1],
+            no_touching: true,
+        }
+    }
+
+    // Preset chosen by name in the host page
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(GameRules::standard()),
+            "quick" => Some(GameRules::quick()),
+            "tournament" => Some(GameRules::tournament()),
+            _ => None,
+        }
+    }
+
+    // Checks that the rules themselves describe a playable game
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 || self.width > 26 || self.height > 26 {
+            return Err(format!("Invalid board size {}x{}", self.width, self.height));
+        }
+        if self.ship_sizes.is_empty() || self.ship_sizes.contains(&0) {
+            return Err("Invalid fleet composition".to_string());
+        }
+        if self
+            .ship_sizes
+            .iter()
+            .any(|&s| s > self.width.max(self.height) as usize)
+            || self.fleet_cells() > self.cells()
+        {
+            return Err("Fleet does not fit in the board".to_string());
+        }
+        Ok(())
+    }
+
+    // Number of cells of the board
+    pub fn cells(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    // Number of ship cells of a fleet, i.e. the hits needed to sink it
+    pub fn fleet_cells(&self) -> usize {
+        self.ship_sizes.iter().sum()
+    }
+
+    pub fn contains(&self, pos: &Coord) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules::standard()
+    }
+}
@@ -1,10 +1,13 @@
 // src/game_actions.rs
 
-use fleetcore::{BaseInputs, Command, FireInputs};
+use fleetcore::{BaseInputs, Command, FireInputs, LobbyAction};
 use methods::{FIRE_ELF, JOIN_ELF, REPORT_ELF, WAVE_ELF, WIN_ELF};
 use risc0_zkvm::{default_prover, guest::env, ExecutorEnv};
 
-use crate::{send_receipt, unmarshal_data, unmarshal_fire, unmarshal_report, FormData};
+use crate::{
+    game_rules, get_players, get_rules, get_seq, get_signing_key, get_timeout, get_turns,
+    send_lobby, send_receipt, unmarshal_data, unmarshal_fire, unmarshal_report, FormData,
+};
 
 fn generate_join_receipt(base_inputs: BaseInputs) -> risc0_zkvm::Receipt {
     let env = ExecutorEnv::builder()
@@ -46,228 +49,244 @@ fn generate_wave_receipt(inputs: BaseInputs) -> risc0_zkvm::Receipt {
     prover.prove(env, WAVE_ELF).unwrap().receipt
 }
 
+fn generate_win_receipt(inputs: BaseInputs) -> risc0_zkvm::Receipt {
+    let env = ExecutorEnv::builder()
+        .write(&inputs)
+        .unwrap()
+        .build()
+        .unwrap();
+    let prover = default_prover();
+    prover.prove(env, WIN_ELF).unwrap().receipt
+}
+
 pub async fn join_game(idata: FormData) -> String {
-    let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
+    let (gameid, fleetid, board, random, page_rules) = match unmarshal_data(&idata) {
         Ok(values) => values,
         Err(err) => return err,
     };
-
-    // TO DO: Rebuild the receipt
-    let mut fleet = Vec::new();
-    for &i in &board {
-        let x = (i % 10) as u8;
-        let y = (i / 10) as u8;
-        fleet.push((x, y));
+    // O join prova as regras com que o jogo foi criado, não as da página
+    let rules = match game_rules(&gameid).await {
+        Ok(rules) => rules,
+        Err(err) => return err,
+    };
+    if (rules.width, rules.height) != (page_rules.width, page_rules.height) {
+        return format!(
+            "Game {} is played on a {}x{} grid, pick its rules and place the fleet again",
+            gameid, rules.width, rules.height
+        );
     }
 
-    // Validar a frota
-    if let Err(e) = validar_frota(&fleet) {
+    // Validar a frota antes de gastar tempo a gerar a prova (o guest volta a validar)
+    if let Err(e) = board.validate(&rules) {
         return format!("Erro na frota: {}", e);
     }
-
-    // Converte Vec<(u8, u8)> para String "x1,y1;x2,y2;..."
-    let fleet_str = fleet
-        .iter()
-        .map(|(x, y)| format!("{},{}", x, y))
-        .collect::<Vec<String>>()
-        .join(";");
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
 
     // Prepara os inputs para o guest
     let base_inputs = BaseInputs {
         fleetid,
-        fleet: fleet_str,
         gameid,
-        board: board.clone(),
+        board,
         random,
+        rules,
+        seq: 0, // o join é sempre o primeiro comando do jogador
+        pubkey: key.verifying_key().to_bytes(), // regista a chave que assina os comandos
     };
 
     // Chama a função síncrona para criar o receipt
     let receipt = generate_join_receipt(base_inputs);
 
-    // Uncomment the following line when you are ready to send the receipt
-    send_receipt(Command::Join, receipt).await
-    // Comment out the following line when you are ready to send the receipt
-    //"OK".to_string()
+    send_receipt(Command::Join, receipt, &key).await
 }
 
 pub async fn fire(idata: FormData) -> String {
-    let (gameid, fleetid, board, random, targetfleet, x, y) = match unmarshal_fire(&idata) {
+    let (gameid, fleetid, board, random, targetfleet, pos) = match unmarshal_fire(&idata) {
         Ok(values) => values,
         Err(err) => return err,
     };
-
-    // TO DO: Rebuild the receipt
-
-    // Reconstrói a fleet string (igual ao join)
-    let mut fleet = Vec::new();
-    for &i in &board {
-        let x_f = (i % 10) as u8;
-        let y_f = (i / 10) as u8;
-        fleet.push((x_f, y_f));
-    }
-    let fleet_str = fleet
-        .iter()
-        .map(|(x, y)| format!("{},{}", x, y))
-        .collect::<Vec<String>>()
-        .join(";");
-
-    // Calcula o índice linear do tiro (dentro dos 10x10)
-    let pos = (y * 10 + x) as u8;
+    let seq = match get_seq(&idata) {
+        Ok(seq) => seq,
+        Err(err) => return err,
+    };
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
 
     // Prepara os inputs para o guest
     let fire_inputs = FireInputs {
         fleetid,
         gameid,
-        fleet: fleet_str,
         board,
         random,
         target: targetfleet,
         pos,
+        seq,
     };
 
     let receipt = generate_fire_receipt(fire_inputs);
 
-    // Uncomment the following line when you are ready to send the receipt
-    send_receipt(Command::Fire, receipt).await
-    // Comment out the following line when you are ready to send the receipt
-    //"OK".to_string()
+    send_receipt(Command::Fire, receipt, &key).await
 }
 
 pub async fn report(idata: FormData) -> String {
-    let (gameid, fleetid, board, random, _report, x, y) = match unmarshal_report(&idata) {
+    // O resultado (Hit/Miss) é decidido pelo guest a partir do board
+    let (gameid, fleetid, board, random, pos) = match unmarshal_report(&idata) {
         Ok(values) => values,
         Err(err) => return err,
     };
-    // TO DO: Rebuild the receipt
-
-    // Reconstrói a fleet string (igual ao join)
-    let mut fleet = Vec::new();
-    for &i in &board {
-        let x_f = (i % 10) as u8;
-        let y_f = (i / 10) as u8;
-        fleet.push((x_f, y_f));
-    }
-    let fleet_str = fleet
-        .iter()
-        .map(|(x, y)| format!("{},{}", x, y))
-        .collect::<Vec<String>>()
-        .join(";");
-
-    // Calcula o índice linear do tiro (dentro dos 10x10)
-    let pos = (y * 10 + x) as u8;
+    let seq = match get_seq(&idata) {
+        Ok(seq) => seq,
+        Err(err) => return err,
+    };
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
 
     // Prepara os inputs para o guest
     let report_inputs = FireInputs {
         fleetid,
         gameid,
-        fleet: fleet_str,
         board,
         random,
+        target: "".to_string(),
         pos,
-        target: _report,
-        //report: _report,
+        seq,
     };
 
     // Chama a função síncrona para criar o receipt
     let receipt = generate_report_receipt(report_inputs);
 
-    // Uncomment the following line when you are ready to send the receipt
-    send_receipt(Command::Report, receipt).await
-    // Comment out the following line when you are ready to send the receipt
-    //"OK".to_string()
+    send_receipt(Command::Report, receipt, &key).await
 }
 
 pub async fn wave(idata: FormData) -> String {
-    let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
+    let (gameid, fleetid, board, random, rules) = match unmarshal_data(&idata) {
         Ok(values) => values,
         Err(err) => return err,
     };
+    let seq = match get_seq(&idata) {
+        Ok(seq) => seq,
+        Err(err) => return err,
+    };
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
 
     // Prepara os inputs para o guest
     let base_inputs = BaseInputs {
         fleetid,
-        fleet: "".to_string(), // ou a fleet string, se necessário
         gameid,
         board,
         random,
+        rules,
+        seq,
+        pubkey: key.verifying_key().to_bytes(),
     };
 
     let receipt = generate_wave_receipt(base_inputs);
 
-    send_receipt(Command::Wave, receipt).await
+    send_receipt(Command::Wave, receipt, &key).await
 }
 
 pub async fn win(idata: FormData) -> String {
-    let (gameid, fleetid, board, random) = match unmarshal_data(&idata) {
+    let (gameid, fleetid, board, random, rules) = match unmarshal_data(&idata) {
         Ok(values) => values,
         Err(err) => return err,
     };
-    // TO DO: Rebuild the receipt
+    let seq = match get_seq(&idata) {
+        Ok(seq) => seq,
+        Err(err) => return err,
+    };
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
+
+    // Prepara os inputs para o guest
+    let base_inputs = BaseInputs {
+        fleetid,
+        gameid,
+        board,
+        random,
+        rules,
+        seq,
+        pubkey: key.verifying_key().to_bytes(),
+    };
 
-    // Uncomment the following line when you are ready to send the receipt
-    //send_receipt(Command::Fire, receipt).await
-    // Comment out the following line when you are ready to send the receipt
-    "OK".to_string()
+    let receipt = generate_win_receipt(base_inputs);
+
+    send_receipt(Command::Win, receipt, &key).await
 }
 
-fn validar_frota(fleet: &[(u8, u8)]) -> Result<(), String> {
-    use std::collections::HashSet;
-
-    // Define os tamanhos dos barcos esperados
-    let barcos_esperados = [5, 4, 3, 2, 2, 1, 1];
-    let mut barcos_encontrados = Vec::new();
-    let mut ocupadas = HashSet::new();
-
-    // Marca todas as posições ocupadas
-    for &(x, y) in fleet {
-        if x >= 10 || y >= 10 {
-            return Err(format!("Navio fora do tabuleiro: ({},{})", x, y));
-        }
-        if !ocupadas.insert((x, y)) {
-            return Err(format!("Sobreposição de navios na posição: ({},{})", x, y));
-        }
-    }
+pub async fn create_game(idata: FormData) -> String {
+    let gameid = match idata.gameid.clone() {
+        Some(gameid) if !gameid.is_empty() => gameid,
+        _ => return "You must provide a Game ID".to_string(),
+    };
+    let rules = match get_rules(&idata) {
+        Ok(rules) => rules,
+        Err(err) => return err,
+    };
+    let turns = match get_turns(&idata) {
+        Ok(turns) => turns,
+        Err(err) => return err,
+    };
+    let (min_players, max_players) = match get_players(&idata) {
+        Ok(players) => players,
+        Err(err) => return err,
+    };
+    let timeout = match get_timeout(&idata) {
+        Ok(timeout) => timeout,
+        Err(err) => return err,
+    };
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
 
-    // Agrupa posições adjacentes em barcos (horizontal ou vertical)
-    let mut restantes: HashSet<_> = fleet.iter().cloned().collect();
-    while !restantes.is_empty() {
-        let &(sx, sy) = restantes.iter().next().unwrap();
-        let mut barco = vec![(sx, sy)];
-        restantes.remove(&(sx, sy));
-
-        // Tenta crescer o barco em ambas as direções
-        let mut expandiu = true;
-        while expandiu {
-            expandiu = false;
-            let mut novos = Vec::new();
-            for &(x, y) in &barco {
-                for &(dx, dy) in &[(1, 0), (0, 1), (-1, 0), (0, -1)] {
-                    let viz = (x.wrapping_add(dx as u8), y.wrapping_add(dy as u8));
-                    if restantes.contains(&viz) {
-                        novos.push(viz);
-                    }
-                }
-            }
-            for pos in novos {
-                if restantes.remove(&pos) {
-                    barco.push(pos);
-                    expandiu = true;
-                }
-            }
-        }
-        barcos_encontrados.push(barco.len());
-    }
+    // Quem cria o jogo fica dono dele: a sua chave assina o start e o cancel
+    let action = LobbyAction::Create {
+        rules,
+        min_players,
+        max_players,
+        owner: key.verifying_key().to_bytes(),
+        turns,
+        timeout,
+    };
+    send_lobby(gameid, action, &key).await
+}
 
-    // Ordena e compara com os tamanhos esperados
-    barcos_encontrados.sort_unstable();
-    let mut esperados = barcos_esperados.to_vec();
-    esperados.sort_unstable();
-    if barcos_encontrados != esperados {
-        return Err(format!(
-            "Frota inválida: tamanhos encontrados {:?}, esperados {:?}",
-            barcos_encontrados, esperados
-        ));
-    }
+pub async fn start_game(idata: FormData) -> String {
+    lobby_action(idata, LobbyAction::Start).await
+}
 
-    Ok(())
+pub async fn cancel_game(idata: FormData) -> String {
+    lobby_action(idata, LobbyAction::Cancel).await
+}
+
+// Vitória reclamada sobre um jogador que deixou esgotar o prazo da jogada
+pub async fn claim_timeout(idata: FormData) -> String {
+    let fleetid = match idata.fleetid.clone() {
+        Some(fleetid) if !fleetid.is_empty() => fleetid,
+        _ => return "You must provide a Fleet ID".to_string(),
+    };
+    lobby_action(idata, LobbyAction::ClaimTimeout { fleetid }).await
+}
+
+async fn lobby_action(idata: FormData, action: LobbyAction) -> String {
+    let gameid = match idata.gameid.clone() {
+        Some(gameid) if !gameid.is_empty() => gameid,
+        _ => return "You must provide a Game ID".to_string(),
+    };
+    let key = match get_signing_key(&idata) {
+        Ok(key) => key,
+        Err(err) => return err,
+    };
+    send_lobby(gameid, action, &key).await
 }
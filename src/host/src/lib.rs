@@ -3,30 +3,65 @@
 #![allow(unused_variables)]
 #![allow(dead_code)]
 
-use percent_encoding;
 use serde::{Deserialize, Serialize};
 mod game_actions;
 
-use fleetcore::{Command, CommunicationData};
+use ed25519_dalek::{Signer, SigningKey};
+use fleetcore::{
+    Board, ChainResponse, Command, CommunicationData, Coord, GameRules, GameState, LobbyAction,
+    LobbyCommand, Phase, TimeoutPolicy, TurnPolicy, TurnTimeout,
+};
 use risc0_zkvm::Receipt;
 use risc0_zkvm::{default_prover, ExecutorEnv};
 use std::error::Error;
 
-pub use game_actions::{fire, join_game, report, wave, win};
+pub use game_actions::{
+    cancel_game, claim_timeout, create_game, fire, join_game, report, start_game, wave, win,
+};
 
-async fn send_receipt(action: Command, receipt: Receipt) -> String {
+async fn send_receipt(action: Command, receipt: Receipt, key: &SigningKey) -> String {
+    // A chain só aceita comandos assinados com a chave registada no join
+    let signature = key.sign(&CommunicationData::signed_message(action, &receipt));
     let client = reqwest::Client::new();
     let res = client
         .post("http://chain0:3001/chain")
         .json(&CommunicationData {
             cmd: action,
             receipt,
+            signature: signature.to_bytes().to_vec(),
         })
         .send()
         .await;
+    chain_answer(res).await
+}
+
+async fn send_lobby(gameid: String, action: LobbyAction, key: &SigningKey) -> String {
+    // Start e cancel só são aceites com a chave de quem criou o jogo
+    let signature = key.sign(&LobbyCommand::signed_message(&gameid, &action));
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://chain0:3001/lobby")
+        .json(&LobbyCommand {
+            gameid,
+            action,
+            signature: signature.to_bytes().to_vec(),
+        })
+        .send()
+        .await;
+    chain_answer(res).await
+}
 
+// A página só precisa de saber se o comando foi aceite; a recusa mostra
+// a mensagem e o código de erro da chain
+async fn chain_answer(res: reqwest::Result<reqwest::Response>) -> String {
     match res {
-        Ok(response) => response.text().await.unwrap(),
+        Ok(response) => match response.json::<ChainResponse>().await {
+            Ok(ChainResponse::Accepted { .. }) => "OK".to_string(),
+            Ok(ChainResponse::Rejected { code, message }) => {
+                format!("{} ({:?})", message, code)
+            }
+            Err(_) => "Invalid response from the chain".to_string(),
+        },
         Err(_) => "Error sending receipt".to_string(),
     }
 }
@@ -41,13 +76,130 @@ pub struct FormData {
     pub y: Option<String>,
     pub rx: Option<String>,
     pub ry: Option<String>,
-    pub report: Option<String>,
     pub board: Option<String>,
     pub shots: Option<String>,
     pub random: Option<String>,
+    pub rules: Option<String>,
+    pub turns: Option<String>,
+    pub min_players: Option<String>,
+    pub max_players: Option<String>,
+    pub timeout: Option<String>,
+    pub on_timeout: Option<String>,
+    pub seq: Option<String>,
+    pub key: Option<String>,
+}
+
+// New Ed25519 key of a player, hex encoded to be kept by the page like the random nonce
+pub fn new_key() -> String {
+    hex::encode(SigningKey::generate(&mut rand::rngs::OsRng).to_bytes())
+}
+
+// Player's signing key, kept by the page between submissions
+pub fn get_signing_key(idata: &FormData) -> Result<SigningKey, String> {
+    let key = idata
+        .key
+        .as_ref()
+        .ok_or_else(|| "You must provide a Signing Key".to_string())?;
+    let bytes: [u8; 32] = hex::decode(key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Invalid Signing Key".to_string())?;
+    Ok(SigningKey::from_bytes(&bytes))
 }
 
-pub fn unmarshal_data(idata: &FormData) -> Result<(String, String, Vec<u8>, String), String> {
+// Game rules preset picked in the page, the standard game when none is given
+pub fn get_rules(idata: &FormData) -> Result<GameRules, String> {
+    match idata.rules.as_deref() {
+        None | Some("") => Ok(GameRules::standard()),
+        Some(name) => {
+            GameRules::by_name(name).ok_or_else(|| format!("Unknown game rules: {}", name))
+        }
+    }
+}
+
+// Turn policy of a new game, round-robin when none is given
+pub fn get_turns(idata: &FormData) -> Result<TurnPolicy, String> {
+    match idata.turns.as_deref() {
+        None | Some("") => Ok(TurnPolicy::RoundRobin),
+        Some(name) => {
+            TurnPolicy::by_name(name).ok_or_else(|| format!("Unknown turn policy: {}", name))
+        }
+    }
+}
+
+// Games of the chain still waiting for players, for the lobby view
+pub async fn lobby_games() -> Vec<GameState> {
+    let res = reqwest::get("http://chain0:3001/games").await;
+    match res {
+        Ok(response) => response
+            .json::<Vec<GameState>>()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|game| game.phase == Phase::Lobby)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Rules the game was created with on the chain, which every join must prove
+pub async fn game_rules(gameid: &str) -> Result<GameRules, String> {
+    let res = reqwest::get(format!("http://chain0:3001/games/{}", gameid)).await;
+    match res {
+        Ok(response) if response.status().is_success() => response
+            .json::<GameState>()
+            .await
+            .map(|game| game.rules)
+            .map_err(|_| "Invalid response from the chain".to_string()),
+        Ok(_) => Err(format!("Game {} not found", gameid)),
+        Err(_) => Err("Error reaching the chain".to_string()),
+    }
+}
+
+// Minimum and maximum number of players of a new game, two players by default
+pub fn get_players(idata: &FormData) -> Result<(u32, u32), String> {
+    let parse = |value: &Option<String>, default: u32| match value.as_deref() {
+        None | Some("") => Ok(default),
+        Some(n) => n
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid number of players: {}", n)),
+    };
+    let min_players = parse(&idata.min_players, 2)?;
+    let max_players = parse(&idata.max_players, min_players)?;
+    Ok((min_players, max_players))
+}
+
+// Turn deadline of a new game, in seconds; none when left empty
+pub fn get_timeout(idata: &FormData) -> Result<Option<TurnTimeout>, String> {
+    let seconds = match idata.timeout.as_deref().map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(seconds) => seconds
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid turn timeout: {}", seconds))?,
+    };
+    let policy = match idata.on_timeout.as_deref() {
+        None | Some("") => TimeoutPolicy::Pass,
+        Some(name) => TimeoutPolicy::by_name(name)
+            .ok_or_else(|| format!("Unknown timeout policy: {}", name))?,
+    };
+    Ok(Some(TurnTimeout { seconds, policy }))
+}
+
+// Sequence number of the player's next command, kept by the page between
+// submissions and only advanced when the chain accepts a command
+pub fn get_seq(idata: &FormData) -> Result<u32, String> {
+    match idata.seq.as_deref() {
+        None | Some("") => Ok(0),
+        Some(seq) => seq
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid sequence number: {}", seq)),
+    }
+}
+
+pub fn unmarshal_data(
+    idata: &FormData,
+) -> Result<(String, String, Board, String, GameRules), String> {
     let gameid = idata
         .gameid
         .clone()
@@ -86,80 +238,82 @@ pub fn unmarshal_data(idata: &FormData) -> Result<(String, String, Vec<u8>, Stri
                 .map(|decoded| {
                     decoded
                         .split(',')
+                        .filter(|s| !s.is_empty())
                         .map(|s| {
-                            s.parse::<u8>()
+                            s.parse::<usize>()
                                 .map_err(|_| "Invalid number in Board Placement".to_string())
                         })
-                        .collect::<Result<Vec<u8>, String>>()
+                        .collect::<Result<Vec<usize>, String>>()
                 })
         })??;
+    let rules = get_rules(idata)?;
+    let board = Board::from_indices(&board, rules.width);
 
-    Ok((gameid, fleetid, board, random))
+    Ok((gameid, fleetid, board, random, rules))
 }
 
-fn get_coordinates(x: &Option<String>, y: &Option<String>) -> Result<(u8, u8), String> {
-    let x: u8 = x
+fn get_coordinates(x: &Option<String>, y: &Option<String>) -> Result<Coord, String> {
+    let x = x
         .as_ref()
-        .ok_or_else(|| "You must provide an X coordinate".to_string())
-        .and_then(|id| {
-            if let Some(first_char) = id.chars().next() {
-                if ('A'..='J').contains(&first_char) {
-                    Ok(first_char as u8 - b'A')
-                } else {
-                    Err("X coordinate must be between A and J".to_string())
-                }
-            } else {
-                Err("Invalid X coordinate".to_string())
-            }
-        })?;
-
-    let y: u8 = y
+        .ok_or_else(|| "You must provide an X coordinate".to_string())?;
+    let y = y
         .as_ref()
-        .ok_or_else(|| "You must provide a Y coordinate".to_string())
-        .and_then(|id| {
-            if let Some(first_char) = id.chars().next() {
-                if ('0'..='9').contains(&first_char) {
-                    Ok(first_char as u8 - b'0')
-                } else {
-                    Err("Y coordinate must be between 0 and 9".to_string())
-                }
-            } else {
-                Err("Invalid Y coordinate".to_string())
-            }
-        })?;
-
-    Ok((x, y))
+        .ok_or_else(|| "You must provide a Y coordinate".to_string())?;
+    format!("{}{}", x.trim(), y.trim()).parse::<Coord>()
 }
 
 pub fn unmarshal_fire(
     idata: &FormData,
-) -> Result<(String, String, Vec<u8>, String, String, u8, u8), String> {
-    let (gameid, fleetid, board, random) = unmarshal_data(idata)?;
-    let (x, y) = get_coordinates(&idata.x, &idata.y)?;
+) -> Result<(String, String, Board, String, String, Coord), String> {
+    let (gameid, fleetid, board, random, _) = unmarshal_data(idata)?;
+    let pos = get_coordinates(&idata.x, &idata.y)?;
     let targetfleet = idata
         .targetfleet
         .clone()
         .ok_or_else(|| "You must provide a Target Fleet ID".to_string())?;
 
-    Ok((gameid, fleetid, board, random, targetfleet, x, y))
+    Ok((gameid, fleetid, board, random, targetfleet, pos))
 }
 
+// The report guest decides hit or miss from the board, so only the position is asked
 pub fn unmarshal_report(
     idata: &FormData,
-) -> Result<(String, String, Vec<u8>, String, String, u8, u8), String> {
-    let (gameid, fleetid, board, random) = unmarshal_data(idata)?;
-    let (x, y) = get_coordinates(&idata.rx, &idata.ry)?;
-    let report = idata
-        .report
-        .clone()
-        .ok_or_else(|| "You must provide a Report value".to_string())
-        .and_then(|r| {
-            if r == "Hit" || r == "Miss" {
-                Ok(r)
-            } else {
-                Err("Report must be either 'Hit' or 'Miss'".to_string())
-            }
-        })?;
+) -> Result<(String, String, Board, String, Coord), String> {
+    let (gameid, fleetid, board, random, _) = unmarshal_data(idata)?;
+    let pos = get_coordinates(&idata.rx, &idata.ry)?;
 
-    Ok((gameid, fleetid, board, random, report, x, y))
+    Ok((gameid, fleetid, board, random, pos))
+}
+
+// After a report is accepted the reported cell is no longer afloat: the report guest
+// committed to the board without it, so the page must move it from board to shots
+// for the next proof to open the new commitment
+pub fn apply_report(
+    board: Option<String>,
+    shots: Option<String>,
+    pos: Coord,
+    width: u8,
+) -> (Option<String>, Option<String>) {
+    let decode = |s: &Option<String>| -> Vec<String> {
+        s.as_deref()
+            .map(|s| {
+                percent_encoding::percent_decode_str(s)
+                    .decode_utf8_lossy()
+                    .into_owned()
+            })
+            .unwrap_or_default()
+            .split(',')
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_string())
+            .collect()
+    };
+    let pos = pos.index(width).to_string();
+    let mut cells = decode(&board);
+    if !cells.contains(&pos) {
+        return (board, shots);
+    }
+    cells.retain(|c| *c != pos);
+    let mut hits = decode(&shots);
+    hits.push(pos);
+    (Some(cells.join(",")), Some(hits.join(",")))
 }
@@ -4,7 +4,7 @@
 #![allow(dead_code)]
 
 use axum::{
-    extract::Form,
+    extract::{Form, Query},
     response::Html,
     routing::{get, post},
     Router,
@@ -13,20 +13,37 @@ use axum::{
 use nanoid::nanoid;
 use tokio::signal;
 
-use host::{fire, join_game, report, wave, win, FormData};
-use std::net::SocketAddr;
+use fleetcore::{GameRules, GameState, TurnPolicy};
+use host::{
+    apply_report, cancel_game, claim_timeout, create_game, fire, get_rules, get_seq, join_game,
+    lobby_games, new_key, report, start_game, unmarshal_report, wave, win, FormData,
+};
+use std::{collections::HashMap, net::SocketAddr};
 
-async fn index() -> Html<String> {
-    render_html(None, None, None, None, None, None)
+// The rules preset can be picked before joining, the grid is drawn with its size
+async fn index(Query(params): Query<HashMap<String, String>>) -> Html<String> {
+    render_html(Page {
+        rules: params.get("rules").cloned(),
+        lobby: lobby_games().await,
+        ..Page::default()
+    })
 }
 
 fn process_input_data(input_data: FormData) -> FormData {
-    match &input_data.random {
+    let input_data = match &input_data.random {
         Some(random) if !random.is_empty() => input_data,
         _ => FormData {
             random: Some(nanoid!(12)),
             ..input_data
         },
+    };
+    // A chave do jogador é criada na primeira submissão e fica na página
+    match &input_data.key {
+        Some(key) if !key.is_empty() => input_data,
+        _ => FormData {
+            key: Some(new_key()),
+            ..input_data
+        },
     }
 }
 
@@ -35,9 +52,28 @@ async fn submit(Form(input_data): Form<FormData>) -> Html<String> {
     let fleetid = input_data.fleetid.clone();
     let data = process_input_data(input_data);
     let random = data.random.clone();
+    let key = data.key.clone();
     let board = data.board.clone();
     let shots = data.shots.clone();
+    let rules = data.rules.clone();
+    let turns = data.turns.clone();
+    let seq = get_seq(&data).unwrap_or(0);
+    let width = get_rules(&data).unwrap_or_default().width;
+    let report_pos = match data.button.as_str() {
+        "Report" => unmarshal_report(&data).ok().map(|(.., pos)| pos),
+        _ => None,
+    };
+    let joining = data.button == "Join";
+    // As operações do lobby não são comandos do jogador, não contam para o seq
+    let lobby = matches!(
+        data.button.as_str(),
+        "Create" | "Start" | "Cancel" | "Claim"
+    );
     let response_text = match data.button.as_str() {
+        "Create" => create_game(data).await,
+        "Start" => start_game(data).await,
+        "Cancel" => cancel_game(data).await,
+        "Claim" => claim_timeout(data).await,
         "Join" => join_game(data).await,
         "Fire" => fire(data).await,
         "Report" => report(data).await,
@@ -45,22 +81,68 @@ async fn submit(Form(input_data): Form<FormData>) -> Html<String> {
         "Win" => win(data).await,
         _ => "Unknown button pressed".to_string(),
     };
-    render_html(gameid, fleetid, random, board, shots, Some(response_text))
+    let (board, shots) = match report_pos {
+        Some(pos) if response_text == "OK" => apply_report(board, shots, pos, width),
+        _ => (board, shots),
+    };
+    // Cada comando aceite avança o número de sequência do jogador
+    let seq = match response_text.as_str() {
+        "OK" if joining => 1,
+        "OK" if !lobby => seq + 1,
+        _ => seq,
+    };
+    render_html(Page {
+        gameid,
+        fleetid,
+        random,
+        board,
+        shots,
+        rules,
+        turns,
+        seq: Some(seq.to_string()),
+        key,
+        response: Some(response_text),
+        lobby: lobby_games().await,
+    })
 }
 
-fn render_html(
+// What the page shows, and keeps in its form between submissions
+#[derive(Default)]
+struct Page {
     gameid: Option<String>,
     fleetid: Option<String>,
     random: Option<String>,
     board: Option<String>,
     shots: Option<String>,
-    response: Option<String>,
-) -> Html<String> {
-    let fleetid = fleetid.unwrap_or("".to_string());
-    let gameid = gameid.unwrap_or("".to_string());
+    rules: Option<String>,
+    turns: Option<String>,
+    seq: Option<String>,
+    key: Option<String>,
+    response: Option<String>, // answer to the last submission, "OK" when accepted
+    lobby: Vec<GameState>,
+}
+
+fn render_html(page: Page) -> Html<String> {
+    let Page {
+        gameid,
+        fleetid,
+        random,
+        board,
+        shots,
+        rules,
+        turns,
+        seq,
+        key,
+        response,
+        lobby,
+    } = page;
+    // Ids e mensagens vêm de formulários e da chain: nunca entram por escapar
+    // numa página que guarda a chave e o board do jogador
+    let fleetid = escape_html(&fleetid.unwrap_or("".to_string()));
+    let gameid = escape_html(&gameid.unwrap_or("".to_string()));
     let response_html = if let Some(response) = response {
         if response == "OK" {
-            if gameid != "" {
+            if !gameid.is_empty() {
                 format!(
                     "Playing Game: <b>{}</b> with fleet's ID: <b>{}</b> ",
                     gameid, fleetid
@@ -69,15 +151,32 @@ fn render_html(
                 "Not in game".to_string()
             }
         } else {
-            format!("<p style='color:red'>{}</p>", response)
+            format!("<p style='color:red'>{}</p>", escape_html(&response))
         }
     } else {
         "".to_string()
     };
-    let random = random.unwrap_or("".to_string());
+    let random = escape_html(&random.unwrap_or("".to_string()));
 
-    let board = board.unwrap_or("".to_string());
-    let shots = shots.unwrap_or("".to_string());
+    // O board e os tiros vão para strings JavaScript: só listas de índices
+    let cells = |cells: Option<String>| -> String {
+        cells
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '%' || *c == ',')
+            .collect()
+    };
+    let board = cells(board);
+    let shots = cells(shots);
+    let rules = rules
+        .filter(|name| GameRules::by_name(name).is_some())
+        .unwrap_or("standard".to_string());
+    let turns = turns
+        .filter(|name| TurnPolicy::by_name(name).is_some())
+        .unwrap_or("round-robin".to_string());
+    let seq = escape_html(&seq.unwrap_or("0".to_string()));
+    let key = escape_html(&key.unwrap_or("".to_string()));
+    let game_rules = GameRules::by_name(&rules).unwrap_or_default();
 
     let path = "host/src/page.html";
     let html = std::fs::read_to_string(path).unwrap();
@@ -87,10 +186,87 @@ fn render_html(
     let html = html.replace("{random}", &random);
     let html = html.replace("{board}", &board);
     let html = html.replace("{shots}", &shots);
+    let html = html.replace("{rules}", &rules);
+    let html = html.replace("{turns}", &turns);
+    let html = html.replace("{seq}", &seq);
+    let html = html.replace("{key}", &key);
+    let html = html.replace("{rules_options}", &rules_options());
+    let html = html.replace("{lobby_html}", &lobby_html(&lobby));
+    let html = html.replace("{width}", &game_rules.width.to_string());
+    let html = html.replace("{height}", &game_rules.height.to_string());
 
     Html(html)
 }
 
+// Text from the chain or a form, safe to place in the page's HTML and attributes
+fn escape_html(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+// Rules presets of the page, with the grid size the page draws for each one
+fn rules_options() -> String {
+    [
+        ("standard", "10x10"),
+        ("quick", "8x8 Quick"),
+        ("tournament", "12x12 Tournament"),
+    ]
+    .iter()
+    .map(|(name, label)| {
+        let rules = GameRules::by_name(name).unwrap_or_default();
+        format!(
+            "<option value=\"{}\" data-width=\"{}\" data-height=\"{}\">{}</option>",
+            name, rules.width, rules.height, label
+        )
+    })
+    .collect()
+}
+
+// Games waiting for players, with the rules, deadlines and seats of each one
+fn lobby_html(games: &[GameState]) -> String {
+    if games.is_empty() {
+        return "<p>No games waiting for players</p>".to_string();
+    }
+    let rows: Vec<String> = games
+        .iter()
+        .map(|game| {
+            let players: Vec<String> = game
+                .players
+                .iter()
+                .map(|p| escape_html(&p.fleetid))
+                .collect();
+            let timeout = match game.timeout {
+                Some(timeout) => format!("{}s, {:?}", timeout.seconds, timeout.policy),
+                None => "none".to_string(),
+            };
+            format!(
+                "<tr><td>{}</td><td>{}x{} {:?}</td><td>{}</td><td>{}/{} (min {})</td><td>{}</td></tr>",
+                escape_html(&game.gameid),
+                game.rules.width,
+                game.rules.height,
+                game.turns,
+                timeout,
+                game.players.len(),
+                game.max_players,
+                game.min_players,
+                players.join(", ")
+            )
+        })
+        .collect();
+    format!(
+        "<table><tr><th>Game</th><th>Rules</th><th>Turn timeout</th><th>Players</th><th>Fleets</th></tr>{}</table>",
+        rows.concat()
+    )
+}
+
 #[tokio::main]
 async fn main() {
     let app = Router::new()
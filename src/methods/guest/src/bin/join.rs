@@ -1,48 +1,33 @@
-use fleetcore::{BaseInputs, BaseJournal};
+use fleetcore::{BaseInputs, Command, JoinJournal, JournalHeader};
 use risc0_zkvm::guest::env;
-use risc0_zkvm::Digest;
-use sha2::{Digest as _, Sha256};
 
 fn main() {
     // read the input
     let input: BaseInputs = env::read();
 
-    // TODO: do something with the input
+    // Sem nonce o digest deixa de esconder a frota
+    assert!(!input.random.is_empty(), "Random nonce em falta");
 
-    let fleet: Vec<(u8, u8)> = input
-        .fleet
-        .split(';')
-        .filter_map(|pair| {
-            let mut coords = pair.split(',');
-            if let (Some(x), Some(y)) = (coords.next(), coords.next()) {
-                Some((x.parse().ok()?, y.parse().ok()?))
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    // Validar se os navios estão dentro dos limites do tabuleiro
-    for &(x, y) in &fleet {
-        assert!(x < 10 && y < 10, "Navio fora do tabuleiro");
+    // As regras ficam no jornal, a chain confirma que são as do jogo
+    if let Err(e) = input.rules.validate() {
+        panic!("Regras inválidas: {}", e);
     }
 
-    // Transformar as coordenadas da frota em bytes para se poder criar o hash
-    let mut fleet_bytes = Vec::new();
-    for &(x, y) in &fleet {
-        fleet_bytes.push(x);
-        fleet_bytes.push(y);
+    // Validar a frota: limites, sobreposição, navios em linha reta e tamanhos
+    if let Err(e) = input.board.validate(&input.rules) {
+        panic!("Erro na frota: {}", e);
     }
 
-    // Gerar o hash da frota
-    let hash = Sha256::digest(&fleet_bytes);
-
-    // Preencher o jornal com o hash da frota
-    let mut output = BaseJournal::default();
-    output.fleetid = input.fleetid.clone();
-    output.gameid = input.gameid.clone();
-    output.fleet = input.fleet.clone();
-    output.board = Digest::try_from(hash.as_slice()).unwrap();
+    // Preencher o jornal com o compromisso (board, random) e as regras
+    let output = JoinJournal {
+        header: JournalHeader::new(Command::Join),
+        fleetid: input.fleetid,
+        gameid: input.gameid,
+        board: input.board.commit(&input.random),
+        rules: input.rules,
+        seq: input.seq,
+        pubkey: input.pubkey,
+    };
 
     // Faz commit do resultado
     env::commit(&output);
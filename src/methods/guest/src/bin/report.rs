@@ -1,15 +1,27 @@
-use fleetcore::{FireInputs, ReportJournal};
+use fleetcore::{Command, FireInputs, JournalHeader, ReportJournal};
 use risc0_zkvm::guest::env;
-//use risc0_zkvm::Digest;
-//use sha2::{Digest as _, Sha256};
 
 fn main() {
-
     // read the input
+    let input: FireInputs = env::read();
+
+    // O resultado vem do board privado, o jogador não o pode escolher
+    let hit = input.board.contains(&input.pos);
+
+    // O novo board deixa de ter a célula atingida
+    let next_board = input.board.without(&input.pos);
 
-    let mut _input: FireInputs = env::read();
-    // TODO: do something with the input
-    let output= ReportJournal::default();
+    let output = ReportJournal {
+        header: JournalHeader::new(Command::Report),
+        fleetid: input.fleetid,
+        gameid: input.gameid,
+        report: if hit { "Hit" } else { "Miss" }.to_string(),
+        pos: input.pos,
+        // Compromisso do board antes do tiro, que a chain compara com o estado guardado
+        board: input.board.commit(&input.random),
+        next_board: next_board.commit(&input.random),
+        seq: input.seq,
+    };
 
     // write public output to the journal
     env::commit(&output);
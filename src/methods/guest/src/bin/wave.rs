@@ -1,18 +1,20 @@
-use fleetcore::{BaseInputs, BaseJournal};
+use fleetcore::{BaseInputs, BaseJournal, Command, JournalHeader};
 use risc0_zkvm::guest::env;
 
 fn main() {
     // read the input
     let input: BaseInputs = env::read();
 
-    // Só pode fazer wave se não tiver barcos vivos (1 = barco não atingido)
-    let has_alive_boat = input.board.iter().any(|&cell| cell == 1);
-    assert!(
-        !has_alive_boat,
-        "Não podes fazer wave: ainda tens barcos vivos!"
-    );
-
-    let output = BaseJournal::default();
+    // O wave passa a vez sem disparar. Uma frota sem barcos à tona já está
+    // fora do jogo pelos reports, por isso o que se prova é só a posse do
+    // board comprometido: o compromisso liga a prova ao board guardado na chain
+    let output = BaseJournal {
+        header: JournalHeader::new(Command::Wave),
+        fleetid: input.fleetid,
+        gameid: input.gameid,
+        board: input.board.commit(&input.random),
+        seq: input.seq,
+    };
 
     // write public output to the journal
     env::commit(&output);
@@ -1,15 +1,24 @@
-use fleetcore::{BaseInputs, BaseJournal};
+use fleetcore::{BaseInputs, BaseJournal, Command, JournalHeader};
 use risc0_zkvm::guest::env;
-//use risc0_zkvm::Digest;
-//use sha2::{Digest as _, Sha256};
 
 fn main() {
-
     // read the input
-    let _input: BaseInputs = env::read();
+    let input: BaseInputs = env::read();
+
+    // Só pode reclamar a vitória quem ainda tem barcos à tona
+    assert!(
+        !input.board.is_empty(),
+        "Não podes ganhar: já não tens barcos à tona!"
+    );
+
+    let output = BaseJournal {
+        header: JournalHeader::new(Command::Win),
+        fleetid: input.fleetid,
+        gameid: input.gameid,
+        board: input.board.commit(&input.random),
+        seq: input.seq,
+    };
 
-    // TODO: do something with the input
-    let output= BaseJournal::default();
     // write public output to the journal
     env::commit(&output);
 }